@@ -1,12 +1,30 @@
-use std::path::PathBuf;
+use std::{io::Write, path::PathBuf};
 
 use orfail::OrFail;
-use tuinix::{Terminal, TerminalEvent, TerminalInput};
+use tuinix::{KeyInput, Terminal};
 
 use crate::{
-    clipboard::Clipboard, config::Config, editor::Editor, editor_command::EditorCommand,
-    tuinix_ext::TerminalFrame, widget_legend::Legend, widget_message::MessageLine,
-    widget_preview::Preview, widget_status::StatusLine, widget_text::TextView,
+    brush::{Brush, BrushKind, BrushShape},
+    buffer::TextPosition,
+    clipboard::{Clipboard, PasteMode},
+    config::Config,
+    editor::Editor,
+    editor_command::EditorCommand,
+    marker::Marker,
+    registers::Registers,
+    selection::SelectionOp,
+    stamp::{Stamp, StampPicker},
+    symmetry::SymmetryMode,
+    system_clipboard,
+    terminal_input::{InputEvent, InputReader},
+    tuinix_ext::TerminalFrame,
+    widget_legend::Legend,
+    widget_message::MessageLine,
+    widget_preview::Preview,
+    widget_stamp_picker::StampPickerView,
+    widget_status::StatusLine,
+    widget_text::TextView,
+    widget_undo_tree::UndoTreeView,
 };
 
 #[derive(Debug)]
@@ -18,11 +36,17 @@ pub struct App {
     message_line: MessageLine,
     preview: Preview,
     legend: Legend,
+    undo_tree: UndoTreeView,
+    stamp_picker: StampPickerView,
+    input: InputReader,
 }
 
 impl App {
     pub fn new(path: PathBuf, config: Config) -> orfail::Result<Self> {
         let terminal = Terminal::new().or_fail()?;
+        let mut stdout = std::io::stdout();
+        write!(stdout, "{}", system_clipboard::ENABLE_BRACKETED_PASTE).or_fail()?;
+        stdout.flush().or_fail()?;
         Ok(Self {
             terminal,
             editor: Editor::new(path, config).or_fail()?,
@@ -31,6 +55,9 @@ impl App {
             message_line: MessageLine,
             preview: Preview::default(),
             legend: Legend::new(),
+            undo_tree: UndoTreeView,
+            stamp_picker: StampPickerView,
+            input: InputReader::default(),
         })
     }
 
@@ -40,8 +67,10 @@ impl App {
         while !self.editor.exit {
             self.render().or_fail()?;
 
-            if let Some(event) = self.terminal.poll_event(None).or_fail()? {
-                self.handle_event(event).or_fail()?;
+            match self.input.read_event(&mut self.terminal).or_fail()? {
+                InputEvent::Key(key) => self.handle_key(key).or_fail()?,
+                InputEvent::Paste(text) => self.handle_paste(&text),
+                InputEvent::Resize => self.editor.dirty.render = true,
             }
         }
         Ok(())
@@ -89,6 +118,20 @@ impl App {
         self.legend.render(&self.editor, &mut subframe).or_fail()?;
         frame.draw(legend_region.position, &subframe);
 
+        let undo_tree_region = self.undo_tree.region(&self.editor, frame.size());
+        let mut subframe = TerminalFrame::new(undo_tree_region.size);
+        self.undo_tree
+            .render(&self.editor, &mut subframe)
+            .or_fail()?;
+        frame.draw(undo_tree_region.position, &subframe);
+
+        let stamp_picker_region = self.stamp_picker.region(&self.editor, frame.size());
+        let mut subframe = TerminalFrame::new(stamp_picker_region.size);
+        self.stamp_picker
+            .render(&self.editor, &mut subframe)
+            .or_fail()?;
+        frame.draw(stamp_picker_region.position, &subframe);
+
         // Set cursor position for text editing
         let cursor_pos = self.text_view.cursor_terminal_position(&self.editor);
         self.terminal.set_cursor(Some(cursor_pos));
@@ -100,46 +143,66 @@ impl App {
         Ok(())
     }
 
-    fn handle_event(&mut self, event: TerminalEvent) -> orfail::Result<()> {
+    fn handle_key(&mut self, key: KeyInput) -> orfail::Result<()> {
         self.editor.clear_message();
-
-        match event {
-            TerminalEvent::Input(input) => {
-                let TerminalInput::Key(key) = input;
-                self.editor.pending_keys.push(key);
-                let root_group = if self.editor.clipboard.is_some() {
-                    &self.editor.config.keybindings.clipboard
-                } else {
-                    &self.editor.config.keybindings.main
-                };
-                match self
-                    .editor
-                    .config
-                    .keybindings
-                    .find(&root_group, &self.editor.pending_keys)
-                {
-                    Err(()) => {
-                        self.editor
-                            .set_message(format!("Undefined: {}", self.editor.pending_keys));
-                        self.editor.pending_keys.clear();
-                    }
-                    Ok(None) => {
-                        self.editor
-                            .set_message(format!("[INPUT] {} ->", self.editor.pending_keys));
-                    }
-                    Ok(Some(command)) => {
-                        self.editor.pending_keys.clear();
-                        self.handle_command(&command.clone()).or_fail()?;
-                    }
-                }
+        self.editor.pending_keys.push(key);
+        let root_group = self.editor.keybindings_group();
+        match self
+            .editor
+            .config
+            .keybindings
+            .find(root_group, &self.editor.pending_keys)
+        {
+            Err(()) => {
+                self.editor
+                    .set_message(format!("Undefined: {}", self.editor.pending_keys));
+                self.editor.pending_keys.clear();
             }
-            TerminalEvent::Resize(_) => {
-                self.editor.dirty.render = true;
+            Ok(None) => {
+                self.editor
+                    .set_message(format!("[INPUT] {} ->", self.editor.pending_keys));
+            }
+            Ok(Some(command)) => {
+                let command = command.clone();
+                self.editor.pending_keys.clear();
+                self.handle_command(&command).or_fail()?;
             }
         }
         Ok(())
     }
 
+    fn handle_paste(&mut self, text: &str) {
+        self.editor.clear_message();
+        let canvas_char = self.editor.config.keybindings.canvas_char();
+        let fg_chars = &self.editor.buffer.filter.fg_chars;
+        if let Some(clipboard) =
+            Clipboard::from_text(text, self.editor.pixel_cursor(), canvas_char, fg_chars)
+        {
+            self.editor.marker = None;
+            self.editor.clipboard = Some(clipboard);
+            self.editor
+                .set_message("Enter clipboard mode (pasted text)");
+        } else {
+            self.editor.set_message("No pixels in pasted text");
+        }
+    }
+
+    // Brushes with too many pixels are rejected (see `Brush::MAX_PIXELS`)
+    fn set_brush(&mut self, brush: Brush) -> bool {
+        if brush.pixel_count() > Brush::MAX_PIXELS {
+            self.editor.set_message(format!(
+                "Brush too large: {} pixels (max {})",
+                brush.pixel_count(),
+                Brush::MAX_PIXELS
+            ));
+            return false;
+        }
+        self.editor.brush = brush;
+        self.editor
+            .set_message(format!("Brush: {}", self.editor.brush));
+        true
+    }
+
     fn handle_command(&mut self, command: &EditorCommand) -> orfail::Result<()> {
         match command {
             EditorCommand::Quit => {
@@ -161,96 +224,344 @@ impl App {
                 // Clear any pending operations or selections
                 self.editor.pending_keys.clear();
                 self.editor.marker = None;
+                self.editor.selection.clear();
                 self.editor.clipboard = None;
+                self.editor.undo_tree_cursor = None;
+                self.editor.stamp_picker = None;
                 self.editor.set_message("Canceled");
             }
             EditorCommand::Undo => {
                 if let Some(i) = self.editor.buffer.undo() {
+                    self.editor.dirty.content = true;
                     self.editor.set_message(format!("Undo: {i} remainings"));
+                } else {
+                    self.editor.set_message("Nothing to undo");
                 }
             }
-            EditorCommand::PrevLine => {
-                self.editor.cursor.row = self.editor.cursor.row.saturating_sub(1);
-                self.editor.dirty.render = true;
-                if let Some(mut marker) = self.editor.marker.take() {
-                    marker.handle_cursor_move(&self.editor);
-                    self.editor.marker = Some(marker);
-                }
-                if let Some(cb) = &mut self.editor.clipboard {
-                    cb.cursor = self.editor.cursor;
+            EditorCommand::Redo => {
+                if let Some(i) = self.editor.buffer.redo() {
+                    self.editor.dirty.content = true;
+                    self.editor.set_message(format!("Redo: {i} remainings"));
+                } else {
+                    self.editor.set_message("Nothing to redo");
                 }
             }
-            EditorCommand::NextLine => {
-                let max_row = self.editor.buffer.lines().count().saturating_sub(1);
-                self.editor.cursor.row = max_row.min(self.editor.cursor.row + 1);
-                self.editor.dirty.render = true;
-                if let Some(mut marker) = self.editor.marker.take() {
-                    marker.handle_cursor_move(&self.editor);
-                    self.editor.marker = Some(marker);
+            EditorCommand::UndoTree => {
+                if self.editor.undo_tree_cursor.take().is_some() {
+                    self.editor.set_message("Close undo tree");
+                } else {
+                    self.editor.undo_tree_cursor = Some(self.editor.buffer.history().current());
+                    self.editor.set_message("Open undo tree");
                 }
-                if let Some(cb) = &mut self.editor.clipboard {
-                    cb.cursor = self.editor.cursor;
+            }
+            EditorCommand::UndoTreeJump => {
+                if let Some(id) = self.editor.undo_tree_cursor.take() {
+                    if self.editor.buffer.jump_to(id) {
+                        self.editor.dirty.content = true;
+                        self.editor.set_message(format!("Jumped to #{id}"));
+                    } else {
+                        self.editor.set_message("No effect");
+                    }
                 }
             }
-            EditorCommand::PrevChar => {
-                self.editor.cursor.col = self.editor.buffer.prev_col(self.editor.cursor);
-                self.editor.dirty.render = true;
-                if let Some(mut marker) = self.editor.marker.take() {
-                    marker.handle_cursor_move(&self.editor);
-                    self.editor.marker = Some(marker);
+            EditorCommand::Stamps => {
+                if self.editor.stamp_picker.take().is_some() {
+                    self.editor.set_message("Close stamps");
+                } else if let Some(dir) = &self.editor.config.stamp_dir {
+                    let (stamps, invalid) = match Stamp::load_dir(dir, &self.editor.buffer.filter) {
+                        Ok(loaded) => loaded,
+                        Err(e) => {
+                            self.editor.set_message(e.message);
+                            return Ok(());
+                        }
+                    };
+                    let skipped = if invalid.is_empty() {
+                        String::new()
+                    } else {
+                        format!(
+                            " (skipped stamps with chars outside the palette: {})",
+                            invalid.join(", ")
+                        )
+                    };
+                    if stamps.is_empty() {
+                        self.editor
+                            .set_message(format!("No stamps in {}{skipped}", dir.display()));
+                    } else {
+                        self.editor.stamp_picker = Some(StampPicker {
+                            stamps,
+                            selected: 0,
+                        });
+                        self.editor.set_message(format!("Open stamps{skipped}"));
+                    }
+                } else {
+                    self.editor.set_message("No stamp_dir configured");
                 }
-                if let Some(cb) = &mut self.editor.clipboard {
-                    cb.cursor = self.editor.cursor;
+            }
+            EditorCommand::StampSelect => {
+                if let Some(picker) = self.editor.stamp_picker.take()
+                    && let Some(stamp) = picker.selected_stamp()
+                {
+                    let canvas_char = self.editor.config.keybindings.canvas_char();
+                    if let Some(clipboard) =
+                        stamp.to_clipboard(self.editor.pixel_cursor(), canvas_char)
+                    {
+                        self.editor.marker = None;
+                        self.editor.clipboard = Some(clipboard);
+                        self.editor
+                            .set_message(format!("Enter clipboard mode (stamp {})", stamp.name));
+                    } else {
+                        self.editor
+                            .set_message(format!("Empty stamp: {}", stamp.name));
+                    }
                 }
             }
-            EditorCommand::NextChar => {
-                self.editor.cursor.col = self.editor.buffer.next_col(self.editor.cursor);
+            EditorCommand::PrevLine if let Some(picker) = &mut self.editor.stamp_picker => {
+                picker.move_cursor(-1);
                 self.editor.dirty.render = true;
-                if let Some(mut marker) = self.editor.marker.take() {
-                    marker.handle_cursor_move(&self.editor);
-                    self.editor.marker = Some(marker);
+            }
+            EditorCommand::NextLine if let Some(picker) = &mut self.editor.stamp_picker => {
+                picker.move_cursor(1);
+                self.editor.dirty.render = true;
+            }
+            EditorCommand::PrevLine if self.editor.undo_tree_cursor.is_some() => {
+                UndoTreeView::move_cursor(&mut self.editor, -1);
+            }
+            EditorCommand::NextLine if self.editor.undo_tree_cursor.is_some() => {
+                UndoTreeView::move_cursor(&mut self.editor, 1);
+            }
+            EditorCommand::PrevLine => {
+                let mut pos = self.editor.cursor;
+                pos.row = pos.row.saturating_sub(1);
+                self.editor.move_cursor(pos);
+            }
+            EditorCommand::NextLine => {
+                let max_row = self.editor.buffer.lines().count().saturating_sub(1);
+                let mut pos = self.editor.cursor;
+                pos.row = max_row.min(pos.row + 1);
+                self.editor.move_cursor(pos);
+            }
+            EditorCommand::PrevChar => {
+                let mut pos = self.editor.cursor;
+                pos.col = self.editor.buffer.prev_col(pos);
+                self.editor.move_cursor(pos);
+            }
+            EditorCommand::NextChar => {
+                let mut pos = self.editor.cursor;
+                pos.col = self.editor.buffer.next_col(pos);
+                self.editor.move_cursor(pos);
+            }
+            EditorCommand::PrevCanvas => {
+                // Canvases are ordered by their top-left corners so that side-by-side ones are visited too
+                let current = self.editor.current_canvas();
+                let from = current.map_or(self.editor.cursor, |c| c.top_left);
+                if let Some(canvas) = self
+                    .editor
+                    .canvases()
+                    .iter()
+                    .filter(|c| c.top_left < from)
+                    .max_by_key(|c| c.top_left)
+                {
+                    self.editor.move_cursor(canvas.top_left);
+                } else {
+                    self.editor.set_message("No previous canvas");
                 }
-                if let Some(cb) = &mut self.editor.clipboard {
-                    cb.cursor = self.editor.cursor;
+            }
+            EditorCommand::NextCanvas => {
+                let current = self.editor.current_canvas();
+                let from = current.map_or(self.editor.cursor, |c| c.top_left);
+                if let Some(canvas) = self
+                    .editor
+                    .canvases()
+                    .iter()
+                    .filter(|c| c.top_left > from)
+                    .min_by_key(|c| c.top_left)
+                {
+                    self.editor.move_cursor(canvas.top_left);
+                } else {
+                    self.editor.set_message("No next canvas");
                 }
             }
+            EditorCommand::NewCanvas(size) => self.editor.insert_canvas(*size),
+            EditorCommand::ResizeCanvas(size, anchor) => self.editor.resize_canvas(*size, *anchor),
+            EditorCommand::InsertRow => self.editor.insert_canvas_row(),
+            EditorCommand::DeleteRow => self.editor.delete_canvas_row(),
+            EditorCommand::InsertColumn => self.editor.insert_canvas_column(),
+            EditorCommand::DeleteColumn => self.editor.delete_canvas_column(),
             EditorCommand::Dot(c) => self.editor.dot(*c).or_fail()?,
-            EditorCommand::MarkStroke => {
-                self.editor.marker = Some(crate::marker::Marker::new_stroke(&self.editor));
+            EditorCommand::MarkStroke { pixel_perfect } => {
+                self.editor.marker = Some(Marker::new_stroke(&self.editor, *pixel_perfect));
                 self.editor.set_message("Stroke marking mode started");
                 self.editor.dirty.render = true;
             }
             EditorCommand::MarkLine => {
-                self.editor.marker = Some(crate::marker::Marker::new_line(&self.editor));
+                self.editor.marker = Some(Marker::new_line(&self.editor));
                 self.editor.set_message("Line marking mode started");
                 self.editor.dirty.render = true;
             }
             EditorCommand::MarkRect => {
-                self.editor.marker = Some(crate::marker::Marker::new_rect(&self.editor));
+                self.editor.marker = Some(Marker::new_rect(&self.editor));
                 self.editor.set_message("Rectangle marking mode started");
                 self.editor.dirty.render = true;
             }
             EditorCommand::MarkFilledRect => {
-                self.editor.marker = Some(crate::marker::Marker::new_filled_rect(&self.editor));
+                self.editor.marker = Some(Marker::new_filled_rect(&self.editor));
                 self.editor
                     .set_message("Filled rectangle marking mode started");
                 self.editor.dirty.render = true;
             }
-            EditorCommand::MarkFill => {
-                self.editor.marker = Some(crate::marker::Marker::new_fill(&self.editor));
+            EditorCommand::MarkFill(mode) => {
+                self.editor.marker = Some(Marker::new_fill(&self.editor, *mode, 0));
                 self.editor.set_message("Fill marking mode started");
                 self.editor.dirty.render = true;
             }
+            EditorCommand::MarkEllipse { circle } => {
+                self.editor.marker = Some(Marker::new_ellipse(&self.editor, false, *circle));
+                self.editor.set_message("Ellipse marking mode started");
+                self.editor.dirty.render = true;
+            }
+            EditorCommand::MarkFilledEllipse { circle } => {
+                self.editor.marker = Some(Marker::new_ellipse(&self.editor, true, *circle));
+                self.editor
+                    .set_message("Filled ellipse marking mode started");
+                self.editor.dirty.render = true;
+            }
+            EditorCommand::MarkPolyline => {
+                self.editor.marker = Some(Marker::new_polygon(&self.editor, false));
+                self.editor.set_message("Polyline marking mode started");
+                self.editor.dirty.render = true;
+            }
+            EditorCommand::MarkFilledPolygon => {
+                self.editor.marker = Some(Marker::new_polygon(&self.editor, true));
+                self.editor
+                    .set_message("Filled polygon marking mode started");
+                self.editor.dirty.render = true;
+            }
+            EditorCommand::MarkCurve => {
+                self.editor.marker = Some(Marker::new_curve(&self.editor));
+                self.editor.set_message("Curve marking mode started");
+                self.editor.dirty.render = true;
+            }
+            EditorCommand::CommitVertex => {
+                let committed = match &mut self.editor.marker {
+                    Some(Marker::Polygon(m)) => m.commit_vertex(),
+                    Some(Marker::Curve(m)) => m.commit_point(),
+                    _ => false,
+                };
+                if committed {
+                    self.editor.set_message("Vertex committed");
+                } else {
+                    self.editor.set_message("No effect");
+                }
+            }
+            EditorCommand::ClosePolygon => {
+                if let Some(Marker::Polygon(m)) = &mut self.editor.marker
+                    && m.close()
+                {
+                    self.editor.set_message("Polygon closed");
+                } else {
+                    self.editor.set_message("No effect");
+                }
+            }
+            EditorCommand::Select(op) => {
+                if self.editor.marker.is_some() {
+                    let positions = self.editor.marked_positions();
+                    self.editor.marker = None;
+                    self.editor.select(*op, positions);
+                    self.editor
+                        .set_message(format!("Selected {} pixels", self.editor.selection.len()));
+                } else {
+                    self.editor.set_message("No marked area");
+                }
+            }
+            EditorCommand::SelectWand { mode, tolerance } => {
+                let marker = Marker::new_fill(&self.editor, *mode, *tolerance);
+                let positions = self.editor.marker_positions(&marker);
+                if positions.is_empty() {
+                    self.editor.set_message("No pixels to select");
+                } else {
+                    self.editor.select(SelectionOp::Add, positions);
+                    self.editor
+                        .set_message(format!("Selected {} pixels", self.editor.selection.len()));
+                }
+            }
+            EditorCommand::MorphSelection(morph, neighborhood) => {
+                if self.editor.morph_selection(*morph, *neighborhood) {
+                    self.editor
+                        .set_message(format!("Selected {} pixels", self.editor.selection.len()));
+                } else {
+                    self.editor.set_message("No effect");
+                }
+            }
+            EditorCommand::InvertSelection => {
+                if let Some(canvas) = self.editor.current_canvas() {
+                    let buffer = &self.editor.buffer;
+                    let area = canvas
+                        .positions(buffer)
+                        .into_iter()
+                        .map(|pos| buffer.to_pixel_position(pos));
+                    self.editor.selection.invert(area);
+                    self.editor
+                        .set_message(format!("Selected {} pixels", self.editor.selection.len()));
+                } else {
+                    self.editor.set_message("Not in a canvas");
+                }
+            }
+            EditorCommand::ClearSelection => {
+                self.editor.selection.clear();
+                self.editor.set_message("Selection cleared");
+            }
+            EditorCommand::Brush(kind) => {
+                let shape = match kind {
+                    BrushKind::Square => BrushShape::Square,
+                    BrushKind::Round => BrushShape::Round,
+                    BrushKind::Clipboard => {
+                        let Some(clipboard) = &self.editor.clipboard else {
+                            self.editor.set_message("No clipboard content");
+                            return Ok(());
+                        };
+                        BrushShape::Custom(clipboard.offsets())
+                    }
+                };
+                let size = self.editor.brush.size;
+                if self.set_brush(Brush { shape, size }) && *kind == BrushKind::Clipboard {
+                    self.editor.clipboard = None;
+                }
+            }
+            EditorCommand::BrushSize(size) => {
+                let shape = self.editor.brush.shape.clone();
+                self.set_brush(Brush { shape, size: *size });
+            }
+            EditorCommand::Symmetry(mode) => {
+                self.editor.symmetry = if self.editor.symmetry == *mode {
+                    SymmetryMode::Off
+                } else {
+                    *mode
+                };
+                self.editor
+                    .set_message(format!("Symmetry: {}", self.editor.symmetry));
+            }
+            EditorCommand::SymmetryAxis { center } => {
+                if *center {
+                    self.editor.symmetry_axis = None;
+                    self.editor.set_message("Symmetry axis: canvas center");
+                } else {
+                    let pixel = self.editor.pixel_cursor();
+                    self.editor.symmetry_axis = Some(TextPosition {
+                        row: pixel.row * 2,
+                        col: pixel.col * 2,
+                    });
+                    self.editor.set_message("Symmetry axis: cursor");
+                }
+            }
             EditorCommand::Save => self.editor.save().or_fail()?,
             EditorCommand::Scope(_) => unreachable!(),
             EditorCommand::Cut => {
                 if let Some(clipboard) = Clipboard::copy_marked_pixels(&mut self.editor) {
                     // Overwrite clipboard pixel positions with canvas char
                     let canvas_char = self.editor.config.keybindings.canvas_char();
-                    let positions_to_clear = clipboard.pixels.keys().copied();
+                    let positions_to_clear = clipboard.pixels().map(|(pos, _)| pos);
                     self.editor
-                        .buffer
-                        .update_bulk(positions_to_clear.map(|pos| (pos, canvas_char)));
+                        .update_pixels(positions_to_clear.map(|pos| (pos, canvas_char)));
 
                     self.editor.clipboard = Some(clipboard);
                     self.editor.set_message("Enter clipboard mode");
@@ -264,15 +575,154 @@ impl App {
             }
             EditorCommand::Paste => {
                 if let Some(clipboard) = &self.editor.clipboard {
-                    if self.editor.buffer.update_bulk(clipboard.pixels()) {
-                        self.editor.dirty.content = true;
-                    }
+                    let canvas_char = self.editor.config.keybindings.canvas_char();
+                    let pixels = clipboard.paste_pixels(self.editor.paste_mode, canvas_char);
+                    self.editor.update_pixels(pixels.into_iter());
+                    self.editor.set_message("Pasted");
+                } else {
+                    self.editor.set_message("No clipboard content");
+                }
+            }
+            EditorCommand::TogglePasteMode => {
+                self.editor.paste_mode = self.editor.paste_mode.toggle();
+                self.editor
+                    .set_message(format!("Paste mode: {}", self.editor.paste_mode));
+            }
+            EditorCommand::PasteTile => {
+                let Some(clipboard) = &self.editor.clipboard else {
+                    self.editor.set_message("No clipboard content");
+                    return Ok(());
+                };
+                let area = if self.editor.marker.is_some() || !self.editor.selection.is_empty() {
+                    self.editor.selected_positions()
+                } else if let Some(canvas) = self.editor.current_canvas() {
+                    canvas.positions(&self.editor.buffer)
+                } else {
+                    self.editor.set_message("No canvas or marked area to tile");
+                    return Ok(());
+                };
+                let canvas_char = self.editor.config.keybindings.canvas_char();
+                let opaque = self.editor.paste_mode == PasteMode::Opaque;
+                let pixels: Vec<_> = area
+                    .into_iter()
+                    .filter_map(|pos| {
+                        let c = clipboard.get_tiled(self.editor.buffer.to_pixel_position(pos));
+                        c.or(opaque.then_some(canvas_char)).map(|c| (pos, c))
+                    })
+                    .collect();
+                self.editor.marker = None;
+                self.editor.update_bulk(pixels.into_iter());
+                self.editor.set_message("Pasted tiles");
+            }
+            EditorCommand::PasteRepeat => {
+                if let Some(clipboard) = &self.editor.clipboard {
+                    let canvas_char = self.editor.config.keybindings.canvas_char();
+                    let pixels = clipboard.paste_pixels(self.editor.paste_mode, canvas_char);
+                    let (_, cols) = clipboard.bounding_size();
+                    self.editor.update_pixels(pixels.into_iter());
+                    let mut pos = self.editor.pixel_cursor();
+                    pos.col += cols;
+                    self.editor
+                        .move_cursor(self.editor.buffer.to_text_position(pos));
                     self.editor.set_message("Pasted");
                 } else {
                     self.editor.set_message("No clipboard content");
                 }
             }
+            EditorCommand::Yank => {
+                if let Some(clipboard) = &self.editor.clipboard {
+                    let canvas_char = self.editor.config.keybindings.canvas_char();
+                    // Like register errors, this must not terminate the editor
+                    match system_clipboard::yank(&clipboard.to_text(canvas_char)) {
+                        Ok(()) => self.editor.set_message("Yanked to system clipboard"),
+                        Err(e) => self.editor.set_message(format!(
+                            "Failed to yank to system clipboard: {}",
+                            e.message
+                        )),
+                    }
+                } else {
+                    self.editor.set_message("No clipboard content");
+                }
+            }
+            EditorCommand::YankTo(name) => {
+                let Some(clipboard) = self.editor.clipboard.clone() else {
+                    self.editor.set_message("No clipboard content");
+                    return Ok(());
+                };
+                // Register errors must not terminate the editor (and lose unsaved work)
+                let result = Registers::load_default().and_then(|mut registers| {
+                    registers.clipboards.insert(name.clone(), clipboard);
+                    registers.save()
+                });
+                match result {
+                    Ok(()) => self
+                        .editor
+                        .set_message(format!("Yanked to register {name}")),
+                    Err(e) => self
+                        .editor
+                        .set_message(format!("Failed to yank to register {name}: {}", e.message)),
+                }
+            }
+            EditorCommand::PasteFrom(name) => {
+                let mut registers = match Registers::load_default() {
+                    Ok(registers) => registers,
+                    Err(e) => {
+                        self.editor.set_message(format!(
+                            "Failed to paste from register {name}: {}",
+                            e.message
+                        ));
+                        return Ok(());
+                    }
+                };
+                if let Some(mut clipboard) = registers.clipboards.remove(name) {
+                    clipboard.cursor = self.editor.pixel_cursor();
+                    self.editor.marker = None;
+                    self.editor.clipboard = Some(clipboard);
+                    self.editor
+                        .set_message(format!("Enter clipboard mode (register {name})"));
+                } else {
+                    self.editor.set_message(format!("Empty register: {name}"));
+                }
+            }
+            EditorCommand::RotateCw => self.transform_clipboard(Clipboard::rotate_cw, "Rotated"),
+            EditorCommand::RotateCcw => self.transform_clipboard(Clipboard::rotate_ccw, "Rotated"),
+            EditorCommand::FlipHorizontal => {
+                self.transform_clipboard(Clipboard::flip_horizontal, "Flipped")
+            }
+            EditorCommand::FlipVertical => {
+                self.transform_clipboard(Clipboard::flip_vertical, "Flipped")
+            }
+            EditorCommand::ScaleUp(factor) => {
+                let mut scaled = true;
+                self.transform_clipboard(|cb| scaled = cb.scale_up(*factor), "Scaled up");
+                if !scaled {
+                    self.editor.set_message(format!(
+                        "Cannot scale up to more than {} pixels",
+                        Clipboard::MAX_PIXELS
+                    ));
+                }
+            }
+            EditorCommand::ScaleDown(factor) => {
+                self.transform_clipboard(|cb| cb.scale_down(*factor), "Scaled down")
+            }
         }
         Ok(())
     }
+
+    fn transform_clipboard(&mut self, f: impl FnOnce(&mut Clipboard), message: &str) {
+        if let Some(clipboard) = &mut self.editor.clipboard {
+            f(clipboard);
+            self.editor.set_message(message);
+        } else {
+            self.editor.set_message("No clipboard content");
+        }
+    }
+}
+
+impl Drop for App {
+    fn drop(&mut self) {
+        let mut stdout = std::io::stdout();
+        let _ = write!(stdout, "{}", system_clipboard::DISABLE_BRACKETED_PASTE);
+        let _ = stdout.flush();
+    }
 }
@@ -0,0 +1,179 @@
+use std::collections::BTreeSet;
+
+use crate::{buffer::TextPosition, marker::ellipse_positions};
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub enum BrushShape {
+    #[default]
+    Square,
+    Round,
+    // `(row, col)` offsets from the cursor (e.g., taken from the clipboard)
+    Custom(Vec<(isize, isize)>),
+}
+
+impl std::fmt::Display for BrushShape {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BrushShape::Square => write!(f, "square"),
+            BrushShape::Round => write!(f, "round"),
+            BrushShape::Custom(_) => write!(f, "custom"),
+        }
+    }
+}
+
+// Shape names accepted by the `brush(...)` command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BrushKind {
+    Square,
+    Round,
+    Clipboard,
+}
+
+impl std::fmt::Display for BrushKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BrushKind::Square => write!(f, "square"),
+            BrushKind::Round => write!(f, "round"),
+            BrushKind::Clipboard => write!(f, "clipboard"),
+        }
+    }
+}
+
+impl std::str::FromStr for BrushKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "square" => Ok(BrushKind::Square),
+            "round" => Ok(BrushKind::Round),
+            "clipboard" => Ok(BrushKind::Clipboard),
+            _ => Err(format!("unknown brush: {s}")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Brush {
+    pub shape: BrushShape,
+    pub size: usize,
+}
+
+impl Default for Brush {
+    fn default() -> Self {
+        Self {
+            shape: BrushShape::Square,
+            size: 1,
+        }
+    }
+}
+
+impl Brush {
+    pub const MAX_SIZE: usize = 16;
+
+    // Keeps `apply()` cheap enough to run on every render (e.g., a large clipboard at a large size)
+    pub const MAX_PIXELS: usize = 4096;
+
+    // Upper bound of `self.offsets().len()`
+    pub fn pixel_count(&self) -> usize {
+        let n = self.size.max(1);
+        match &self.shape {
+            BrushShape::Square | BrushShape::Round => n * n,
+            BrushShape::Custom(offsets) => offsets.len() * n * n,
+        }
+    }
+
+    // `(row, col)` offsets of the pixels painted around the cursor
+    pub fn offsets(&self) -> Vec<(isize, isize)> {
+        let n = self.size.max(1) as isize;
+        let half = (n - 1) / 2;
+        match &self.shape {
+            BrushShape::Square => (0..n)
+                .flat_map(|row| (0..n).map(move |col| (row - half, col - half)))
+                .collect(),
+            BrushShape::Round => {
+                let max = TextPosition {
+                    row: n as usize - 1,
+                    col: n as usize - 1,
+                };
+                ellipse_positions(TextPosition::default(), max, true)
+                    .into_iter()
+                    .map(|pos| (pos.row as isize - half, pos.col as isize - half))
+                    .collect()
+            }
+            // Each pixel of a custom brush becomes a `size`x`size` block
+            BrushShape::Custom(offsets) => offsets
+                .iter()
+                .flat_map(|&(row, col)| {
+                    (0..n).flat_map(move |dr| {
+                        (0..n).map(move |dc| (row * n + dr - half, col * n + dc - half))
+                    })
+                })
+                .collect(),
+        }
+    }
+
+    // Pixels painted by moving the brush over `pixels`
+    pub fn apply(&self, pixels: impl Iterator<Item = TextPosition>) -> Vec<TextPosition> {
+        let offsets = self.offsets();
+        pixels
+            .flat_map(|pixel| {
+                offsets.iter().filter_map(move |&(dr, dc)| {
+                    Some(TextPosition {
+                        row: pixel.row.checked_add_signed(dr)?,
+                        col: pixel.col.checked_add_signed(dc)?,
+                    })
+                })
+            })
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect()
+    }
+}
+
+impl std::fmt::Display for Brush {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.shape, self.size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(brush: &Brush, rows: usize, cols: usize) -> Vec<String> {
+        let pixels = brush.apply(std::iter::once(TextPosition { row: 2, col: 2 }));
+        crate::test_util::render(&pixels, rows, cols)
+    }
+
+    #[test]
+    fn shapes() {
+        let mut brush = Brush::default();
+        assert_eq!(render(&brush, 5, 5)[2], "..#..");
+
+        brush.size = 3;
+        assert_eq!(
+            render(&brush, 5, 5),
+            [".....", ".###.", ".###.", ".###.", "....."]
+        );
+
+        brush.shape = BrushShape::Round;
+        brush.size = 5;
+        assert_eq!(
+            render(&brush, 5, 5),
+            [".###.", "#####", "#####", "#####", ".###."]
+        );
+
+        brush.shape = BrushShape::Custom(vec![(0, 0), (1, 1)]);
+        brush.size = 2;
+        assert_eq!(
+            render(&brush, 6, 6),
+            ["......", "......", "..##..", "..##..", "....##", "....##"]
+        );
+        assert_eq!(brush.pixel_count(), brush.offsets().len());
+
+        brush.size = Brush::MAX_SIZE;
+        assert!(brush.pixel_count() <= Brush::MAX_PIXELS);
+        brush.shape = BrushShape::Custom(vec![(0, 0); 17]);
+        assert!(brush.pixel_count() > Brush::MAX_PIXELS);
+    }
+}
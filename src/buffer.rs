@@ -1,25 +1,24 @@
-use std::{collections::HashSet, num::NonZeroUsize};
+use std::{cell::OnceCell, collections::HashSet, num::NonZeroUsize};
 
 use unicode_width::UnicodeWidthChar;
 
-#[derive(Debug)]
+use crate::{
+    canvas::Canvas,
+    history::{UndoHistory, UndoNodeId, UndoOperation},
+};
+
+#[derive(Debug, Default)]
 pub struct TextBuffer {
     lines: Vec<String>,
-    undo_stack: Vec<UndoOperation>,
-    undo_index: usize,
-    undoing: bool,
+    history: UndoHistory,
+    // Detected lazily and cleared whenever `lines` changes
+    canvases: OnceCell<Vec<Canvas>>,
     pub filter: TextBufferFilter,
 }
 
 impl TextBuffer {
     pub fn new() -> Self {
-        Self {
-            lines: Vec::new(),
-            undo_stack: Vec::new(),
-            undo_index: 0,
-            undoing: false,
-            filter: TextBufferFilter::default(),
-        }
+        Self::default()
     }
 
     pub fn get_char_at(&self, pos: TextPosition) -> Option<char> {
@@ -49,6 +48,20 @@ impl TextBuffer {
         self.lines.iter().map(|s| s.as_ref())
     }
 
+    // Returns each char of the row along with its starting column
+    pub fn char_cols(&self, row: usize) -> impl '_ + Iterator<Item = (usize, char)> {
+        let mut current_col = 0;
+        self.lines
+            .get(row)
+            .into_iter()
+            .flat_map(|line| line.chars())
+            .map(move |c| {
+                let col = current_col;
+                current_col += self.filter.apply(c).width().unwrap_or(0);
+                (col, c)
+            })
+    }
+
     pub fn cols(&self, row: usize) -> usize {
         self.lines.get(row).map_or(0, |line| {
             line.chars()
@@ -57,61 +70,89 @@ impl TextBuffer {
         })
     }
 
-    pub fn prev_col(&self, TextPosition { row, col }: TextPosition) -> usize {
-        if row >= self.rows() || col == 0 {
-            return 0;
+    // A "pixel" is a single char regardless of its display width.
+    // Pixel columns beyond the end of a line are treated as one-column chars.
+    pub fn to_pixel_position(&self, pos: TextPosition) -> TextPosition {
+        let mut col = 0;
+        let mut line_cols = 0;
+        for (start, c) in self.char_cols(pos.row) {
+            line_cols = start + self.filter.apply(c).width().unwrap_or(0);
+            if line_cols > pos.col {
+                // Positions in the middle of a wide char belong to that char
+                return TextPosition { row: pos.row, col };
+            }
+            col += 1;
         }
+        TextPosition {
+            row: pos.row,
+            col: col + (pos.col - line_cols),
+        }
+    }
 
-        let line = &self.lines[row];
-        let mut current_col = 0;
-        let mut prev_col = 0;
-
-        for c in line.chars() {
-            let c = self.filter.apply(c);
-            let char_width = c.width().unwrap_or(0);
-            if current_col >= col {
-                return prev_col;
+    pub fn to_text_position(&self, pixel: TextPosition) -> TextPosition {
+        let mut pixels = 0;
+        let mut line_cols = 0;
+        for (start, c) in self.char_cols(pixel.row) {
+            if pixels == pixel.col {
+                return TextPosition {
+                    row: pixel.row,
+                    col: start,
+                };
             }
-            prev_col = current_col;
-            current_col += char_width;
+            pixels += 1;
+            line_cols = start + self.filter.apply(c).width().unwrap_or(0);
+        }
+        TextPosition {
+            row: pixel.row,
+            col: line_cols + (pixel.col - pixels),
         }
+    }
 
-        prev_col
+    fn pixels(&self, row: usize) -> usize {
+        self.lines.get(row).map_or(0, |line| line.chars().count())
     }
 
-    pub fn next_col(&self, TextPosition { row, col }: TextPosition) -> usize {
-        if row >= self.rows() {
-            return col;
+    pub fn prev_col(&self, pos: TextPosition) -> usize {
+        if pos.row >= self.rows() || pos.col == 0 {
+            return 0;
         }
 
-        let line = &self.lines[row];
-        let mut current_col = 0;
+        let pixel = self.to_pixel_position(pos);
+        let col = pixel.col.min(self.pixels(pos.row)).saturating_sub(1);
+        self.to_text_position(TextPosition { col, ..pixel }).col
+    }
 
-        for c in line.chars() {
-            let c = self.filter.apply(c);
-            let char_width = c.width().unwrap_or(0);
-            if current_col > col {
-                return current_col;
-            }
-            current_col += char_width;
-            if current_col > col {
-                return current_col;
-            }
+    pub fn next_col(&self, pos: TextPosition) -> usize {
+        if pos.row >= self.rows() {
+            return pos.col;
         }
 
-        current_col
+        let pixel = self.to_pixel_position(pos);
+        let col = (pixel.col + 1).min(self.pixels(pos.row));
+        self.to_text_position(TextPosition { col, ..pixel }).col
     }
 
     pub fn rows(&self) -> usize {
         self.lines.len()
     }
 
+    pub fn canvases(&self) -> &[Canvas] {
+        self.canvases.get_or_init(|| Canvas::detect(self))
+    }
+
     pub fn set_text(&mut self, text: String) {
         self.lines = text.lines().map(|s| s.to_owned()).collect();
-        // Clear undo history when setting new text
-        // TODO: keep history
-        self.undo_stack.clear();
-        self.undo_index = 0;
+        self.canvases.take();
+        // Clear undo history when setting new text (see also `set_history()`)
+        self.history = UndoHistory::default();
+    }
+
+    pub fn history(&self) -> &UndoHistory {
+        &self.history
+    }
+
+    pub fn set_history(&mut self, history: UndoHistory) {
+        self.history = history;
     }
 
     pub fn update(&mut self, pos: TextPosition, new: char) -> bool {
@@ -119,10 +160,131 @@ impl TextBuffer {
     }
 
     pub fn update_bulk(&mut self, updates: impl Iterator<Item = (TextPosition, char)>) -> bool {
-        let mut any_updated = false;
-        let mut bulk_undo_updates = Vec::new();
+        let updates = self.apply_updates(updates, true);
+        if updates.is_empty() {
+            return false;
+        }
+
+        self.history.push(UndoOperation::BulkUpdate { updates });
+        true
+    }
+
+    // Replaces `count` lines starting from `row` with `new_lines`
+    pub fn replace_lines(&mut self, row: usize, count: usize, new_lines: Vec<String>) -> bool {
+        let row = row.min(self.rows());
+        let end = (row + count).min(self.rows());
+        if self.lines[row..end] == new_lines[..] {
+            return false;
+        }
+
+        let old_lines = self
+            .lines
+            .splice(row..end, new_lines.iter().cloned())
+            .collect();
+        self.canvases.take();
+        self.history.push(UndoOperation::ReplaceLines {
+            row,
+            old_lines,
+            new_lines,
+        });
+        true
+    }
+
+    pub fn undo(&mut self) -> Option<usize> {
+        let mut history = std::mem::take(&mut self.history);
+        let result = history.undo().map(|op| self.revert_operation(op));
+        self.history = history;
+        result.map(|()| self.history.depth(self.history.current()))
+    }
+
+    pub fn redo(&mut self) -> Option<usize> {
+        let mut history = std::mem::take(&mut self.history);
+        let result = history.redo().map(|op| self.apply_operation(op));
+        self.history = history;
+        result.map(|()| self.history.redo_count())
+    }
+
+    // Restores the state of the given undo tree node
+    pub fn jump_to(&mut self, id: UndoNodeId) -> bool {
+        if self.history.node(id).is_none() || self.history.current() == id {
+            return false;
+        }
+
+        let history = std::mem::take(&mut self.history);
+        self.traverse(&history, id);
+        self.history = history;
+        self.history.set_current(id);
+        true
+    }
+
+    // Returns a copy of this buffer in the state of the given undo tree node (without history)
+    pub fn snapshot(&self, id: UndoNodeId) -> Self {
+        let mut buffer = Self {
+            lines: self.lines.clone(),
+            history: UndoHistory::default(),
+            canvases: OnceCell::new(),
+            filter: self.filter.clone(),
+        };
+        if self.history.node(id).is_some() {
+            buffer.traverse(&self.history, id);
+        }
+        buffer
+    }
+
+    fn traverse(&mut self, history: &UndoHistory, to: UndoNodeId) {
+        let (ups, downs) = history.path(history.current(), to);
+        for id in ups {
+            self.revert_operation(&history.node(id).expect("bug").operation);
+        }
+        for id in downs {
+            self.apply_operation(&history.node(id).expect("bug").operation);
+        }
+    }
+
+    fn apply_operation(&mut self, op: &UndoOperation) {
+        match op {
+            UndoOperation::BulkUpdate { updates } => {
+                self.apply_updates(updates.iter().map(|&(pos, _, new)| (pos, new)), false);
+            }
+            UndoOperation::ReplaceLines {
+                row,
+                old_lines,
+                new_lines,
+            } => {
+                self.lines
+                    .splice(*row..*row + old_lines.len(), new_lines.iter().cloned());
+                self.canvases.take();
+            }
+        }
+    }
+
+    fn revert_operation(&mut self, op: &UndoOperation) {
+        match op {
+            UndoOperation::BulkUpdate { updates } => {
+                // Restore in reverse order so that repeated positions end up with their oldest char
+                self.apply_updates(updates.iter().rev().map(|&(pos, old, _)| (pos, old)), false);
+            }
+            UndoOperation::ReplaceLines {
+                row,
+                old_lines,
+                new_lines,
+            } => {
+                self.lines
+                    .splice(*row..*row + new_lines.len(), old_lines.iter().cloned());
+                self.canvases.take();
+            }
+        }
+    }
+
+    // Returns the applied (position, old char, new char) tuples.
+    // Undo and redo pass `fg_only = false` so that recorded chars are always restored.
+    fn apply_updates(
+        &mut self,
+        updates: impl Iterator<Item = (TextPosition, char)>,
+        fg_only: bool,
+    ) -> Vec<(TextPosition, char, char)> {
+        let mut applied = Vec::new();
 
-        // Collect all valid updates first
         for (pos, new) in updates {
             if !(pos.row < self.rows() && pos.col < self.cols(pos.row)) {
                 continue;
@@ -131,50 +293,27 @@ impl TextBuffer {
             let mut current_cols = 0;
             for (i, c) in self.lines[pos.row].char_indices() {
                 if current_cols >= pos.col {
-                    assert_eq!(current_cols, pos.col);
-                    if new == c || !self.filter.fg_chars.contains(&c) {
+                    // Positions in the middle of a wide char are ignored
+                    if current_cols != pos.col
+                        || new == c
+                        || (fg_only && !self.filter.fg_chars.contains(&c))
+                    {
                         break;
                     }
 
-                    // Record the operation for bulk undo
-                    bulk_undo_updates.push((pos, c)); // old char
+                    applied.push((pos, c, new));
 
-                    // Perform the actual update
+                    // Replacing a char may change the width of the line
+                    self.canvases.take();
                     self.lines[pos.row].remove(i);
                     self.lines[pos.row].insert(i, new);
-                    any_updated = true;
                     break;
                 }
-                current_cols += c.width().unwrap_or(0);
-            }
-        }
-
-        // Add bulk undo operation to stack if any updates occurred
-        if any_updated && !bulk_undo_updates.is_empty() {
-            self.undo_stack.push(UndoOperation::BulkUpdate {
-                updates: bulk_undo_updates,
-            });
-            if !self.undoing {
-                self.undo_index = self.undo_stack.len();
-            }
-        }
-
-        any_updated
-    }
-
-    pub fn undo(&mut self) -> Option<usize> {
-        self.undo_index = self.undo_index.checked_sub(1)?;
-        self.undoing = true;
-        let undo_op = self.undo_stack[self.undo_index].clone();
-        match undo_op {
-            UndoOperation::BulkUpdate { updates } => {
-                // Restore all characters in bulk
-                self.update_bulk(updates.into_iter());
+                current_cols += self.filter.apply(c).width().unwrap_or(0);
             }
         }
-        self.undoing = false;
 
-        Some(self.undo_index)
+        applied
     }
 }
 
@@ -204,12 +343,7 @@ impl std::str::FromStr for TextPosition {
     }
 }
 
-#[derive(Debug, Clone)]
-enum UndoOperation {
-    BulkUpdate { updates: Vec<(TextPosition, char)> },
-}
-
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct TextBufferFilter {
     pub bg_char: Option<char>,
     pub fg_chars: HashSet<char>,
@@ -225,3 +359,115 @@ impl TextBufferFilter {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn buffer(text: &str) -> TextBuffer {
+        let mut buffer = TextBuffer::new();
+        buffer.filter.fg_chars = [' ', 'o', '#'].into_iter().collect();
+        buffer.set_text(text.to_owned());
+        buffer
+    }
+
+    fn pos(row: usize, col: usize) -> TextPosition {
+        TextPosition { row, col }
+    }
+
+    fn text(buffer: &TextBuffer) -> String {
+        buffer.lines().collect::<Vec<_>>().join("\n")
+    }
+
+    #[test]
+    fn undo_redo() {
+        let mut buffer = buffer("|   |");
+        assert!(buffer.update(pos(0, 1), 'o'));
+        assert!(buffer.update_bulk([(pos(0, 2), 'o'), (pos(0, 2), '#')].into_iter()));
+        assert_eq!(text(&buffer), "|o# |");
+
+        assert_eq!(buffer.undo(), Some(1));
+        assert_eq!(text(&buffer), "|o  |");
+        assert_eq!(buffer.undo(), Some(0));
+        assert_eq!(text(&buffer), "|   |");
+        assert_eq!(buffer.undo(), None);
+
+        assert_eq!(buffer.redo(), Some(1));
+        assert_eq!(text(&buffer), "|o  |");
+        assert_eq!(buffer.redo(), Some(0));
+        assert_eq!(text(&buffer), "|o# |");
+        assert_eq!(buffer.redo(), None);
+
+        // A new edit starts a new branch
+        assert_eq!(buffer.undo(), Some(1));
+        assert!(buffer.update(pos(0, 3), '#'));
+        assert_eq!(buffer.redo(), None);
+        assert_eq!(text(&buffer), "|o #|");
+
+        // The undone branch is still reachable
+        assert_eq!(
+            buffer.history().tree_order(),
+            [(0, 0), (1, 1), (2, 2), (3, 2)]
+        );
+        assert_eq!(text(&buffer.snapshot(2)), "|o# |");
+        assert!(buffer.jump_to(2));
+        assert_eq!(text(&buffer), "|o# |");
+        assert!(buffer.jump_to(3));
+        assert_eq!(text(&buffer), "|o #|");
+        assert_eq!(buffer.undo(), Some(1));
+        assert_eq!(buffer.redo(), Some(0));
+        assert_eq!(text(&buffer), "|o #|");
+    }
+
+    #[test]
+    fn undo_non_fg_char() {
+        // e.g., a char that is not in the palette written by an older version
+        let mut buffer = buffer("|  |");
+        assert!(buffer.update(pos(0, 1), 'x'));
+        assert!(!buffer.update(pos(0, 1), 'o'));
+        assert_eq!(buffer.undo(), Some(0));
+        assert_eq!(text(&buffer), "|  |");
+        assert_eq!(buffer.redo(), Some(0));
+        assert_eq!(text(&buffer), "|x |");
+    }
+
+    #[test]
+    fn canvases_follow_edits() {
+        let mut buffer = buffer("+--+\n|  |\n+--+");
+        assert_eq!(buffer.canvases().len(), 1);
+
+        assert!(buffer.replace_lines(3, 0, vec!["+--+".to_owned(), "|  |".to_owned()]));
+        assert!(buffer.replace_lines(5, 0, vec!["+--+".to_owned()]));
+        assert_eq!(buffer.canvases().len(), 2);
+
+        assert_eq!(buffer.undo(), Some(1));
+        assert_eq!(buffer.canvases().len(), 1);
+        assert_eq!(buffer.redo(), Some(0));
+        assert_eq!(buffer.canvases().len(), 2);
+    }
+
+    #[test]
+    fn pixel_positions() {
+        let mut buffer = TextBuffer::new();
+        buffer.filter.fg_chars = ['⬜', '🟨'].into_iter().collect();
+        buffer.set_text("|⬜🟨|".to_owned());
+
+        assert_eq!(buffer.to_pixel_position(pos(0, 1)), pos(0, 1));
+        assert_eq!(buffer.to_pixel_position(pos(0, 2)), pos(0, 1));
+        assert_eq!(buffer.to_pixel_position(pos(0, 3)), pos(0, 2));
+        assert_eq!(buffer.to_pixel_position(pos(0, 5)), pos(0, 3));
+        assert_eq!(buffer.to_pixel_position(pos(0, 8)), pos(0, 6));
+        assert_eq!(buffer.to_text_position(pos(0, 2)), pos(0, 3));
+        assert_eq!(buffer.to_text_position(pos(0, 5)), pos(0, 7));
+
+        assert_eq!(buffer.next_col(pos(0, 1)), 3);
+        assert_eq!(buffer.next_col(pos(0, 5)), 6);
+        assert_eq!(buffer.prev_col(pos(0, 3)), 1);
+        assert_eq!(buffer.prev_col(pos(0, 9)), 5);
+
+        // Updates in the middle of a wide char are ignored
+        assert!(!buffer.update(pos(0, 2), '🟨'));
+        assert!(buffer.update(pos(0, 1), '🟨'));
+        assert_eq!(text(&buffer), "|🟨🟨|");
+    }
+}
@@ -0,0 +1,564 @@
+use unicode_width::UnicodeWidthChar;
+
+use crate::{
+    buffer::{TextBuffer, TextPosition},
+    config::FrameSize,
+};
+
+// Drawable area of a text file.
+//
+// Canvases are detected per block, where a block is the content of a ``` fence
+// (or the whole file if it has no fences):
+// - Each framed area (e.g., `+---+` / `|   |` / `+---+`) in the block is a canvas
+// - If the block has no frames, the block itself is a canvas
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Canvas {
+    // Inclusive bounds of the interior
+    pub top_left: TextPosition,
+    pub bottom_right: TextPosition,
+    pub frame: Option<Frame>,
+    // Whether the canvas is inside of a ``` fence (rather than the whole file)
+    pub fenced: bool,
+}
+
+impl Canvas {
+    pub fn detect(buffer: &TextBuffer) -> Vec<Self> {
+        let mut canvases = Vec::new();
+        let lines: Vec<&str> = buffer.lines().collect();
+        let mut fenced = false;
+        let mut block_start = None;
+        for (row, line) in lines.iter().enumerate() {
+            if !line.trim_start().starts_with("```") {
+                continue;
+            }
+            fenced = true;
+            if let Some(start) = block_start.take() {
+                Self::detect_in_block(buffer, start, row, true, &mut canvases);
+            } else {
+                block_start = Some(row + 1);
+            }
+        }
+        if !fenced {
+            Self::detect_in_block(buffer, 0, lines.len(), false, &mut canvases);
+        }
+        canvases
+    }
+
+    fn detect_in_block(
+        buffer: &TextBuffer,
+        start: usize,
+        end: usize,
+        fenced: bool,
+        canvases: &mut Vec<Self>,
+    ) {
+        let n = canvases.len();
+        let mut row = start;
+        while row < end {
+            if let Some(canvas) = Self::detect_framed(buffer, row, end, fenced) {
+                row = canvas.bottom_right.row + 2;
+                canvases.push(canvas);
+            } else {
+                row += 1;
+            }
+        }
+        if canvases.len() > n {
+            return;
+        }
+
+        let Some(right) = (start..end)
+            .filter_map(|row| buffer.char_cols(row).last().map(|(col, _)| col))
+            .max()
+        else {
+            return;
+        };
+        canvases.push(Self {
+            top_left: TextPosition { row: start, col: 0 },
+            bottom_right: TextPosition {
+                row: end - 1,
+                col: right,
+            },
+            frame: None,
+            fenced,
+        });
+    }
+
+    fn detect_framed(buffer: &TextBuffer, top: usize, end: usize, fenced: bool) -> Option<Self> {
+        let (left, right) = Self::frame_edge(buffer, top)?;
+        let mut bottom = top + 1;
+        while bottom < end {
+            if Self::frame_edge(buffer, bottom) == Some((left, right)) {
+                break;
+            }
+            let mut chars = buffer.char_cols(bottom);
+            let is_side = |c: Option<(usize, char)>, col| {
+                c.is_some_and(|(c_col, c)| c_col == col && is_frame_side(c))
+            };
+            if !is_side(chars.find(|(col, _)| *col >= left), left) || !is_side(chars.last(), right)
+            {
+                return None;
+            }
+            bottom += 1;
+        }
+        if bottom == end || bottom == top + 1 {
+            return None;
+        }
+
+        // The rightmost interior column is the start of the last char before the right side
+        let interior_right = buffer
+            .char_cols(top + 1)
+            .take_while(|(col, _)| *col < right)
+            .last()
+            .map(|(col, _)| col)?;
+        (left < interior_right).then_some(Self {
+            top_left: TextPosition {
+                row: top + 1,
+                col: left + 1,
+            },
+            bottom_right: TextPosition {
+                row: bottom - 1,
+                col: interior_right,
+            },
+            frame: Some(Frame {
+                top_left: TextPosition {
+                    row: top,
+                    col: left,
+                },
+                bottom_right: TextPosition {
+                    row: bottom,
+                    col: right,
+                },
+            }),
+            fenced,
+        })
+    }
+
+    // Returns the columns of the corners if the row is a horizontal frame edge (e.g., `+----+`)
+    fn frame_edge(buffer: &TextBuffer, row: usize) -> Option<(usize, usize)> {
+        let mut chars = buffer.char_cols(row).skip_while(|(_, c)| c.is_whitespace());
+        let (left, first) = chars.next()?;
+        let mut right = None;
+        for (col, c) in chars {
+            match right {
+                None if is_frame_corner(c) => right = Some(col),
+                None if is_frame_horizontal(c) => {}
+                Some(_) if c.is_whitespace() => {}
+                _ => return None,
+            }
+        }
+        let right = right?;
+        (is_frame_corner(first) && left + 1 < right).then_some((left, right))
+    }
+
+    pub fn contains(&self, pos: TextPosition) -> bool {
+        (self.top_left.row..=self.bottom_right.row).contains(&pos.row)
+            && (self.top_left.col..=self.bottom_right.col).contains(&pos.col)
+    }
+
+    pub fn is_on_frame(&self, pos: TextPosition) -> bool {
+        self.frame.is_some_and(|frame| {
+            (frame.top_left.row..=frame.bottom_right.row).contains(&pos.row)
+                && (frame.top_left.col..=frame.bottom_right.col).contains(&pos.col)
+                && !self.contains(pos)
+        })
+    }
+
+    pub fn clamp(&self, pos: TextPosition) -> TextPosition {
+        TextPosition {
+            row: pos.row.clamp(self.top_left.row, self.bottom_right.row),
+            col: pos.col.clamp(self.top_left.col, self.bottom_right.col),
+        }
+    }
+
+    // Rows occupied by this canvas (including the frame)
+    pub fn row_range(&self) -> std::ops::Range<usize> {
+        match self.frame {
+            Some(frame) => frame.top_left.row..frame.bottom_right.row + 1,
+            None => self.top_left.row..self.bottom_right.row + 1,
+        }
+    }
+
+    // Interior chars of each row
+    pub fn pixels(&self, buffer: &TextBuffer) -> Vec<Vec<char>> {
+        (self.top_left.row..=self.bottom_right.row)
+            .map(|row| {
+                buffer
+                    .char_cols(row)
+                    .filter(|(col, _)| (self.top_left.col..=self.bottom_right.col).contains(col))
+                    .map(|(_, c)| c)
+                    .collect()
+            })
+            .collect()
+    }
+
+    // Positions of the interior chars
+    pub fn positions(&self, buffer: &TextBuffer) -> Vec<TextPosition> {
+        (self.top_left.row..=self.bottom_right.row)
+            .flat_map(|row| {
+                buffer
+                    .char_cols(row)
+                    .filter(|(col, _)| (self.top_left.col..=self.bottom_right.col).contains(col))
+                    .map(move |(col, _)| TextPosition { row, col })
+            })
+            .collect()
+    }
+
+    // Index of the pixel at `pos` within `self.pixels()[pos.row - self.top_left.row]`
+    pub fn pixel_index(&self, buffer: &TextBuffer, pos: TextPosition) -> usize {
+        buffer
+            .char_cols(pos.row)
+            .filter(|(col, _)| (self.top_left.col..pos.col).contains(col))
+            .count()
+    }
+
+    // Builds the lines in `self.row_range()` so that the interior consists of `pixels`
+    pub fn to_lines(&self, buffer: &TextBuffer, pixels: &[Vec<char>]) -> Vec<String> {
+        let split = |row: usize, left: usize, right: usize| {
+            let mut prefix = String::new();
+            let mut suffix = String::new();
+            for (col, c) in buffer.char_cols(row) {
+                if col < left {
+                    prefix.push(c);
+                } else if col > right {
+                    suffix.push(c);
+                }
+            }
+            (prefix, suffix)
+        };
+
+        // The first interior row is used as the template of the other rows
+        let (prefix, suffix) = split(self.top_left.row, self.top_left.col, self.bottom_right.col);
+        let width = pixels
+            .iter()
+            .map(|row| row.iter().map(|c| c.width().unwrap_or(0)).sum())
+            .max()
+            .unwrap_or(0);
+        let edge = |row: usize, frame: Frame| {
+            let (edge_prefix, edge_suffix) = split(row, frame.top_left.col, frame.bottom_right.col);
+            let mut chars = buffer
+                .char_cols(row)
+                .map(|(_, c)| c)
+                .skip(edge_prefix.chars().count());
+            let left = chars.next().unwrap_or('+');
+            let horizontal = chars
+                .next()
+                .filter(|c| is_frame_horizontal(*c))
+                .unwrap_or('-');
+            let right = buffer
+                .char_cols(row)
+                .find(|(col, _)| *col == frame.bottom_right.col)
+                .map_or('+', |(_, c)| c);
+            format!(
+                "{edge_prefix}{left}{}{right}{edge_suffix}",
+                horizontal.to_string().repeat(width)
+            )
+        };
+
+        let mut lines = Vec::with_capacity(pixels.len() + 2);
+        if let Some(frame) = self.frame {
+            lines.push(edge(frame.top_left.row, frame));
+        }
+        for row in pixels {
+            lines.push(format!(
+                "{prefix}{}{suffix}",
+                row.iter().collect::<String>()
+            ));
+        }
+        if let Some(frame) = self.frame {
+            lines.push(edge(frame.bottom_right.row, frame));
+        }
+        lines
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Anchor {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+impl Anchor {
+    const NAMES: [(&str, Anchor); 9] = [
+        ("top-left", Anchor::TopLeft),
+        ("top", Anchor::Top),
+        ("top-right", Anchor::TopRight),
+        ("left", Anchor::Left),
+        ("center", Anchor::Center),
+        ("right", Anchor::Right),
+        ("bottom-left", Anchor::BottomLeft),
+        ("bottom", Anchor::Bottom),
+        ("bottom-right", Anchor::BottomRight),
+    ];
+
+    // (horizontal, vertical) where 0 = start, 1 = middle, 2 = end
+    fn alignment(self) -> (isize, isize) {
+        let i = Self::NAMES
+            .iter()
+            .position(|(_, a)| *a == self)
+            .expect("bug") as isize;
+        (i % 3, i / 3)
+    }
+}
+
+impl std::fmt::Display for Anchor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (name, _) = Self::NAMES.iter().find(|(_, a)| a == self).expect("bug");
+        write!(f, "{name}")
+    }
+}
+
+impl std::str::FromStr for Anchor {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::NAMES
+            .iter()
+            .find(|(name, _)| *name == s)
+            .map(|(_, a)| *a)
+            .ok_or_else(|| format!("Invalid anchor: '{s}'"))
+    }
+}
+
+// Resizes `pixels` to `size` keeping the `anchor` side fixed and filling new pixels with `fill`
+pub fn resize_pixels(pixels: &mut Vec<Vec<char>>, size: FrameSize, anchor: Anchor, fill: char) {
+    // Adds (or removes if negative) `delta` items to the front of `items`
+    fn grow_front<T: Clone>(items: &mut Vec<T>, delta: isize, item: T) {
+        if delta >= 0 {
+            items.splice(0..0, std::iter::repeat_n(item, delta as usize));
+        } else {
+            items.drain(0..delta.unsigned_abs().min(items.len()));
+        }
+    }
+
+    let (h_align, v_align) = anchor.alignment();
+    let width = pixels.iter().map(|row| row.len()).max().unwrap_or(0);
+    let blank_row = vec![fill; width];
+    let dh = size.height as isize - pixels.len() as isize;
+    grow_front(pixels, dh * v_align / 2, blank_row.clone());
+    pixels.resize(size.height, blank_row);
+
+    let dw = size.width as isize - width as isize;
+    for row in pixels {
+        row.resize(width, fill);
+        grow_front(row, dw * h_align / 2, fill);
+        row.resize(size.width, fill);
+    }
+}
+
+// Positions of the corners of a canvas frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Frame {
+    pub top_left: TextPosition,
+    pub bottom_right: TextPosition,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameStyle {
+    Ascii,
+    None,
+}
+
+impl std::str::FromStr for FrameStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ascii" => Ok(FrameStyle::Ascii),
+            "none" => Ok(FrameStyle::None),
+            _ => Err(format!(
+                "Invalid frame style: expected 'ascii' or 'none', got '{s}'"
+            )),
+        }
+    }
+}
+
+// Returns the lines of a blank canvas filled with `canvas_char`
+pub fn blank_canvas_lines(
+    size: FrameSize,
+    style: FrameStyle,
+    canvas_char: char,
+    markdown: bool,
+) -> Vec<String> {
+    let row = canvas_char.to_string().repeat(size.width);
+    let mut lines = Vec::with_capacity(size.height + 4);
+    if markdown {
+        lines.push("```".to_owned());
+    }
+    match style {
+        FrameStyle::Ascii => {
+            let edge = format!(
+                "+{}+",
+                "-".repeat(size.width * canvas_char.width().unwrap_or(1))
+            );
+            lines.push(edge.clone());
+            lines.extend((0..size.height).map(|_| format!("|{row}|")));
+            lines.push(edge);
+        }
+        FrameStyle::None => {
+            lines.extend((0..size.height).map(|_| row.clone()));
+        }
+    }
+    if markdown {
+        lines.push("```".to_owned());
+    }
+    lines
+}
+
+pub fn is_frame_corner(c: char) -> bool {
+    matches!(c, '+' | '┌' | '┐' | '└' | '┘' | '╔' | '╗' | '╚' | '╝')
+}
+
+pub fn is_frame_horizontal(c: char) -> bool {
+    matches!(c, '-' | '─' | '═')
+}
+
+pub fn is_frame_side(c: char) -> bool {
+    matches!(c, '|' | '│' | '║')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn buffer(text: &str) -> TextBuffer {
+        let mut buffer = TextBuffer::new();
+        buffer.filter.fg_chars = [' ', 'o', '⬛', '🟨'].into_iter().collect();
+        buffer.set_text(text.to_owned());
+        buffer
+    }
+
+    fn canvas(
+        top: usize,
+        left: usize,
+        bottom: usize,
+        right: usize,
+        frame_right: Option<usize>,
+        fenced: bool,
+    ) -> Canvas {
+        Canvas {
+            top_left: TextPosition {
+                row: top,
+                col: left,
+            },
+            bottom_right: TextPosition {
+                row: bottom,
+                col: right,
+            },
+            frame: frame_right.map(|col| Frame {
+                top_left: TextPosition {
+                    row: top - 1,
+                    col: left - 1,
+                },
+                bottom_right: TextPosition {
+                    row: bottom + 1,
+                    col,
+                },
+            }),
+            fenced,
+        }
+    }
+
+    #[test]
+    fn detect_canvases() {
+        let text = "\
+# Title
+```
++---+
+| o |
+|   |
++---+
+```
+prose
+```
+oo
+ooo
+```
+```
++----+
+|⬛🟨|
++----+
+```
+";
+        assert_eq!(
+            Canvas::detect(&buffer(text)),
+            [
+                canvas(3, 1, 4, 3, Some(4), true),
+                canvas(9, 0, 10, 2, None, true),
+                canvas(14, 1, 14, 3, Some(5), true),
+            ]
+        );
+
+        // No fences
+        assert_eq!(
+            Canvas::detect(&buffer("+--+\n|  |\n+--+\n")),
+            [canvas(1, 1, 1, 2, Some(3), false)]
+        );
+        assert_eq!(
+            Canvas::detect(&buffer("ooo\no\n")),
+            [canvas(0, 0, 1, 2, None, false)]
+        );
+    }
+
+    #[test]
+    fn blank_canvas() {
+        let size = FrameSize {
+            width: 2,
+            height: 1,
+        };
+        let lines = blank_canvas_lines(size, FrameStyle::Ascii, '⬛', true);
+        assert_eq!(lines, ["```", "+----+", "|⬛⬛|", "+----+", "```"]);
+        assert_eq!(
+            Canvas::detect(&buffer(&lines.join("\n"))),
+            [canvas(2, 1, 2, 3, Some(5), true)]
+        );
+
+        let lines = blank_canvas_lines(size, FrameStyle::None, ' ', false);
+        assert_eq!(lines, ["  "]);
+
+        // Empty or huge sizes are rejected
+        assert!("3x2".parse::<FrameSize>().is_ok());
+        assert!("0x3".parse::<FrameSize>().is_err());
+        assert!("5x0".parse::<FrameSize>().is_err());
+        assert!("100000x100000".parse::<FrameSize>().is_err());
+    }
+
+    #[test]
+    fn resize_canvas() {
+        let buffer = buffer("```\n+---+\n|o  |\n| o |\n+---+\n```\n");
+        let [canvas] = Canvas::detect(&buffer)[..] else {
+            panic!()
+        };
+        let mut pixels = canvas.pixels(&buffer);
+        assert_eq!(pixels, [['o', ' ', ' '], [' ', 'o', ' ']]);
+
+        let size = FrameSize {
+            width: 5,
+            height: 1,
+        };
+        resize_pixels(&mut pixels, size, Anchor::Bottom, ' ');
+        assert_eq!(pixels, [[' ', ' ', 'o', ' ', ' ']]);
+        assert_eq!(
+            canvas.to_lines(&buffer, &pixels),
+            ["+-----+", "|  o  |", "+-----+"]
+        );
+    }
+
+    #[test]
+    fn frame_positions() {
+        let canvas = canvas(1, 1, 2, 3, Some(4), false);
+        let pos = |row, col| TextPosition { row, col };
+        assert!(canvas.is_on_frame(pos(0, 0)));
+        assert!(canvas.is_on_frame(pos(0, 2)));
+        assert!(canvas.is_on_frame(pos(2, 4)));
+        assert!(canvas.is_on_frame(pos(3, 4)));
+        assert!(!canvas.is_on_frame(pos(2, 3)));
+        assert!(!canvas.is_on_frame(pos(3, 5)));
+        assert!(!canvas.is_on_frame(pos(4, 0)));
+    }
+}
@@ -1,37 +1,123 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashSet};
 
-use crate::{buffer::TextPosition, editor::Editor};
+use crate::{buffer::TextPosition, config::FrameSize, editor::Editor, history::single_char};
 
-#[derive(Debug)]
+// Whether canvas-char gaps inside the bounding box overwrite the destination when pasting
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum PasteMode {
+    #[default]
+    Transparent,
+    Opaque,
+}
+
+impl PasteMode {
+    pub fn toggle(self) -> Self {
+        match self {
+            PasteMode::Transparent => PasteMode::Opaque,
+            PasteMode::Opaque => PasteMode::Transparent,
+        }
+    }
+}
+
+impl std::fmt::Display for PasteMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PasteMode::Transparent => write!(f, "TRANSPARENT"),
+            PasteMode::Opaque => write!(f, "OPAQUE"),
+        }
+    }
+}
+
+// Positions are in pixel units (see `TextBuffer::to_pixel_position()`)
+#[derive(Debug, Clone)]
 pub struct Clipboard {
     pub original_cursor: TextPosition,
     pub cursor: TextPosition,
-    pub pixels: BTreeMap<TextPosition, char>,
+    pixels: BTreeMap<TextPosition, char>,
+    // Cached result of `bounds()` (queried for every rendered cell)
+    bounds: (TextPosition, TextPosition),
 }
 
 impl Clipboard {
+    pub const MAX_SCALE_FACTOR: usize = 8;
+
+    // Bounds repeated scale-ups (as large as the largest canvas)
+    pub const MAX_PIXELS: usize = FrameSize::MAX * FrameSize::MAX;
+
+    fn new(cursor: TextPosition, pixels: BTreeMap<TextPosition, char>) -> Self {
+        let mut this = Self {
+            original_cursor: cursor,
+            cursor,
+            pixels: BTreeMap::new(),
+            bounds: Default::default(),
+        };
+        this.set_pixels(pixels);
+        this
+    }
+
     pub fn copy_marked_pixels(editor: &mut Editor) -> Option<Self> {
         let canvas_char = editor.config.keybindings.canvas_char();
-        let marker = editor.marker.take()?;
-        let pixels: BTreeMap<_, _> = marker
-            .marked_positions()
+        let positions = editor.selected_positions();
+        editor.marker = None;
+        editor.selection.clear();
+        let pixels: BTreeMap<_, _> = positions
+            .into_iter()
             .filter_map(|pos| {
                 editor
                     .buffer
                     .get_char_at(pos)
                     .filter(|c| *c != canvas_char)
-                    .map(|c| (pos, c))
+                    .map(|c| (editor.buffer.to_pixel_position(pos), c))
             })
             .collect();
         if pixels.is_empty() {
             return None;
         }
-        let cursor = editor.cursor;
-        Some(Self {
-            original_cursor: cursor,
-            cursor,
-            pixels,
-        })
+        Some(Self::new(editor.pixel_cursor(), pixels))
+    }
+
+    // Chars equal to `canvas_char` or not in `fg_chars` are treated as transparent
+    // (the editor could neither overwrite nor undo the latter once pasted)
+    pub fn from_text(
+        text: &str,
+        cursor: TextPosition,
+        canvas_char: char,
+        fg_chars: &HashSet<char>,
+    ) -> Option<Self> {
+        let mut pixels = BTreeMap::new();
+        for (row, line) in text.lines().enumerate() {
+            for (col, c) in line.chars().enumerate() {
+                if c != canvas_char && fg_chars.contains(&c) {
+                    let pos = TextPosition {
+                        row: cursor.row + row,
+                        col: cursor.col + col,
+                    };
+                    pixels.insert(pos, c);
+                }
+            }
+        }
+        if pixels.is_empty() {
+            return None;
+        }
+        Some(Self::new(cursor, pixels))
+    }
+
+    // Returns the bounding box of the pixels, filling the gaps with `canvas_char`
+    pub fn to_text(&self, canvas_char: char) -> String {
+        let (min, max) = self.bounds();
+        let mut text = String::new();
+        for row in min.row..=max.row {
+            for col in min.col..=max.col {
+                let c = self
+                    .pixels
+                    .get(&TextPosition { row, col })
+                    .copied()
+                    .unwrap_or(canvas_char);
+                text.push(c);
+            }
+            text.push('\n');
+        }
+        text
     }
 
     pub fn pixels(&self) -> impl '_ + Iterator<Item = (TextPosition, char)> {
@@ -58,4 +144,387 @@ impl Clipboard {
         let rel_pos = TextPosition { row, col };
         self.pixels.get(&rel_pos).copied()
     }
+
+    // Same as `get()` except that gaps in the bounding box are `canvas_char` in opaque mode
+    pub fn get_with_mode(
+        &self,
+        pos: TextPosition,
+        mode: PasteMode,
+        canvas_char: char,
+    ) -> Option<char> {
+        self.get(pos).or_else(|| {
+            let opaque = mode == PasteMode::Opaque && self.is_on_grid(pos);
+            opaque.then_some(canvas_char)
+        })
+    }
+
+    // Pixels to be written by pasting
+    pub fn paste_pixels(&self, mode: PasteMode, canvas_char: char) -> Vec<(TextPosition, char)> {
+        if mode == PasteMode::Transparent {
+            return self.pixels().collect();
+        }
+
+        let (min, max) = self.bounds();
+        (min.row..=max.row)
+            .flat_map(|row| (min.col..=max.col).map(move |col| TextPosition { row, col }))
+            .filter_map(|pos| {
+                let row = (pos.row + self.cursor.row).checked_sub(self.original_cursor.row)?;
+                let col = (pos.col + self.cursor.col).checked_sub(self.original_cursor.col)?;
+                let c = self.pixels.get(&pos).copied().unwrap_or(canvas_char);
+                Some((TextPosition { row, col }, c))
+            })
+            .collect()
+    }
+
+    // Whether `pos` is in the bounding box
+    fn is_on_grid(&self, pos: TextPosition) -> bool {
+        let (min, max) = self.bounds();
+        let row = pos.row as isize + self.original_cursor.row as isize - self.cursor.row as isize;
+        let col = pos.col as isize + self.original_cursor.col as isize - self.cursor.col as isize;
+        (min.row as isize..=max.row as isize).contains(&row)
+            && (min.col as isize..=max.col as isize).contains(&col)
+    }
+
+    // Size of the bounding box as `(rows, cols)`
+    pub fn bounding_size(&self) -> (usize, usize) {
+        let (min, max) = self.bounds();
+        (max.row - min.row + 1, max.col - min.col + 1)
+    }
+
+    // Same as `get()` except that the pixels repeat in every direction with the bounding box as the period
+    pub fn get_tiled(&self, pos: TextPosition) -> Option<char> {
+        let (min, _) = self.bounds();
+        let (rows, cols) = self.bounding_size();
+        let row = pos.row as isize + self.original_cursor.row as isize - self.cursor.row as isize;
+        let col = pos.col as isize + self.original_cursor.col as isize - self.cursor.col as isize;
+        let pos = TextPosition {
+            row: min.row + (row - min.row as isize).rem_euclid(rows as isize) as usize,
+            col: min.col + (col - min.col as isize).rem_euclid(cols as isize) as usize,
+        };
+        self.pixels.get(&pos).copied()
+    }
+
+    // Top-left and bottom-right positions of the pixels
+    fn bounds(&self) -> (TextPosition, TextPosition) {
+        self.bounds
+    }
+
+    fn set_pixels(&mut self, pixels: BTreeMap<TextPosition, char>) {
+        let min = TextPosition {
+            row: pixels.keys().map(|pos| pos.row).min().unwrap_or(0),
+            col: pixels.keys().map(|pos| pos.col).min().unwrap_or(0),
+        };
+        let max = TextPosition {
+            row: pixels.keys().map(|pos| pos.row).max().unwrap_or(0),
+            col: pixels.keys().map(|pos| pos.col).max().unwrap_or(0),
+        };
+        self.pixels = pixels;
+        self.bounds = (min, max);
+    }
+
+    pub fn pixel_count(&self) -> usize {
+        self.pixels.len()
+    }
+
+    pub fn rotate_cw(&mut self) {
+        self.transform(|x, y| (-y, x));
+    }
+
+    pub fn rotate_ccw(&mut self) {
+        self.transform(|x, y| (y, -x));
+    }
+
+    pub fn flip_horizontal(&mut self) {
+        self.transform(|x, y| (-x, y));
+    }
+
+    pub fn flip_vertical(&mut self) {
+        self.transform(|x, y| (x, -y));
+    }
+
+    // Returns `false` without scaling if the result would have more than `MAX_PIXELS` pixels
+    pub fn scale_up(&mut self, factor: usize) -> bool {
+        let count = factor
+            .checked_mul(factor)
+            .and_then(|n| n.checked_mul(self.pixel_count()));
+        if count.is_none_or(|n| n > Self::MAX_PIXELS) {
+            return false;
+        }
+
+        let k = factor as isize;
+        let scaled: Vec<_> = self
+            .relative_pixels()
+            .into_iter()
+            .flat_map(|(x, y, c)| {
+                (0..k).flat_map(move |dy| (0..k).map(move |dx| (x * k + dx, y * k + dy, c)))
+            })
+            .collect();
+        self.set_relative_pixels(scaled);
+        true
+    }
+
+    // Each `factor`x`factor` block becomes the most frequent char in it,
+    // or transparent if empty pixels are the majority
+    pub fn scale_down(&mut self, factor: usize) {
+        let k = factor as isize;
+        let mut blocks = BTreeMap::<(isize, isize), BTreeMap<char, usize>>::new();
+        for (x, y, c) in self.relative_pixels() {
+            let block = blocks
+                .entry((x.div_euclid(k), y.div_euclid(k)))
+                .or_default();
+            *block.entry(c).or_default() += 1;
+        }
+        let scaled: Vec<_> = blocks
+            .into_iter()
+            .filter_map(|((x, y), counts)| {
+                let empty = factor * factor - counts.values().sum::<usize>();
+                let (c, count) = counts.into_iter().rev().max_by_key(|(_, count)| *count)?;
+                (count >= empty).then_some((x, y, c))
+            })
+            .collect();
+        self.set_relative_pixels(scaled);
+    }
+
+    // Applies `f` to the pixel offsets from `original_cursor`
+    fn transform(&mut self, f: impl Fn(isize, isize) -> (isize, isize)) {
+        let transformed: Vec<_> = self
+            .relative_pixels()
+            .into_iter()
+            .map(|(x, y, c)| {
+                let (x, y) = f(x, y);
+                (x, y, c)
+            })
+            .collect();
+        self.set_relative_pixels(transformed);
+    }
+
+    // Returns `(row, col)` offsets of the pixels from `original_cursor`
+    pub fn offsets(&self) -> Vec<(isize, isize)> {
+        self.relative_pixels()
+            .into_iter()
+            .map(|(x, y, _)| (y, x))
+            .collect()
+    }
+
+    // Returns `(x, y, char)` offsets from `original_cursor`
+    fn relative_pixels(&self) -> Vec<(isize, isize, char)> {
+        let origin_row = self.original_cursor.row as isize;
+        let origin_col = self.original_cursor.col as isize;
+        self.pixels
+            .iter()
+            .map(|(pos, &c)| {
+                let x = pos.col as isize - origin_col;
+                let y = pos.row as isize - origin_row;
+                (x, y, c)
+            })
+            .collect()
+    }
+
+    fn set_relative_pixels(&mut self, pixels: Vec<(isize, isize, char)>) {
+        let origin_row = self.original_cursor.row as isize;
+        let origin_col = self.original_cursor.col as isize;
+        let positioned: Vec<_> = pixels
+            .into_iter()
+            .map(|(x, y, c)| (origin_row + y, origin_col + x, c))
+            .collect();
+
+        // Shift the pixels and the origin together if pixels go beyond the text origin
+        let shift_row = -positioned.iter().map(|p| p.0).min().unwrap_or(0).min(0);
+        let shift_col = -positioned.iter().map(|p| p.1).min().unwrap_or(0).min(0);
+        self.original_cursor = TextPosition {
+            row: (origin_row + shift_row) as usize,
+            col: (origin_col + shift_col) as usize,
+        };
+        let pixels = positioned
+            .into_iter()
+            .map(|(row, col, c)| {
+                let pos = TextPosition {
+                    row: (row + shift_row) as usize,
+                    col: (col + shift_col) as usize,
+                };
+                (pos, c)
+            })
+            .collect();
+        self.set_pixels(pixels);
+    }
+}
+
+impl nojson::DisplayJson for Clipboard {
+    fn fmt(&self, f: &mut nojson::JsonFormatter<'_, '_>) -> std::fmt::Result {
+        f.object(|f| {
+            f.member(
+                "original_cursor",
+                [self.original_cursor.row, self.original_cursor.col],
+            )?;
+            f.member(
+                "pixels",
+                nojson::json(|f| {
+                    f.array(|f| {
+                        for (pos, c) in &self.pixels {
+                            f.element(nojson::json(|f| {
+                                f.array(|f| {
+                                    f.element(pos.row)?;
+                                    f.element(pos.col)?;
+                                    f.element(c.to_string())
+                                })
+                            }))?;
+                        }
+                        Ok(())
+                    })
+                }),
+            )
+        })
+    }
+}
+
+impl<'text, 'raw> TryFrom<nojson::RawJsonValue<'text, 'raw>> for Clipboard {
+    type Error = nojson::JsonParseError;
+
+    fn try_from(value: nojson::RawJsonValue<'text, 'raw>) -> Result<Self, Self::Error> {
+        let (row, col) = value.to_member("original_cursor")?.required()?.try_into()?;
+        let original_cursor = TextPosition { row, col };
+        let mut pixels = BTreeMap::new();
+        for raw_pixel in value.to_member("pixels")?.required()?.to_array()? {
+            let (row, col, c): (usize, usize, String) = raw_pixel.try_into()?;
+            let c =
+                single_char(&c).ok_or_else(|| raw_pixel.invalid("expected a single character"))?;
+            pixels.insert(TextPosition { row, col }, c);
+        }
+        Ok(Self::new(original_cursor, pixels))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clipboard(pixels: &[(usize, usize, char)]) -> Clipboard {
+        let cursor = TextPosition { row: 5, col: 5 };
+        let pixels = pixels
+            .iter()
+            .map(|&(row, col, c)| (TextPosition { row, col }, c))
+            .collect();
+        Clipboard::new(cursor, pixels)
+    }
+
+    fn pixels(clipboard: &Clipboard) -> Vec<(usize, usize, char)> {
+        let mut pixels: Vec<_> = clipboard
+            .pixels()
+            .map(|(pos, c)| (pos.row, pos.col, c))
+            .collect();
+        pixels.sort();
+        pixels
+    }
+
+    #[test]
+    fn transforms() {
+        let mut cb = clipboard(&[(5, 5, 'a'), (5, 6, 'b'), (6, 5, 'c')]);
+        cb.rotate_cw();
+        assert_eq!(pixels(&cb), [(5, 4, 'c'), (5, 5, 'a'), (6, 5, 'b')]);
+        cb.rotate_ccw();
+        assert_eq!(pixels(&cb), [(5, 5, 'a'), (5, 6, 'b'), (6, 5, 'c')]);
+        cb.flip_horizontal();
+        assert_eq!(pixels(&cb), [(5, 4, 'b'), (5, 5, 'a'), (6, 5, 'c')]);
+        cb.flip_vertical();
+        assert_eq!(pixels(&cb), [(4, 5, 'c'), (5, 4, 'b'), (5, 5, 'a')]);
+
+        // Double-width chars
+        let mut cb = clipboard(&[(5, 5, '⬛'), (5, 6, '🟨')]);
+        cb.rotate_cw();
+        assert_eq!(pixels(&cb), [(5, 5, '⬛'), (6, 5, '🟨')]);
+        cb.flip_vertical();
+        cb.rotate_ccw();
+        assert_eq!(pixels(&cb), [(5, 4, '🟨'), (5, 5, '⬛')]);
+    }
+
+    #[test]
+    fn scales() {
+        let mut cb = clipboard(&[(5, 5, 'a'), (5, 6, 'b')]);
+        assert!(cb.scale_up(2));
+        assert_eq!(cb.bounding_size(), (2, 4));
+        assert_eq!(
+            pixels(&cb),
+            [
+                (5, 5, 'a'),
+                (5, 6, 'a'),
+                (5, 7, 'b'),
+                (5, 8, 'b'),
+                (6, 5, 'a'),
+                (6, 6, 'a'),
+                (6, 7, 'b'),
+                (6, 8, 'b')
+            ]
+        );
+        cb.scale_down(2);
+        assert_eq!(pixels(&cb), [(5, 5, 'a'), (5, 6, 'b')]);
+        assert_eq!(cb.bounding_size(), (1, 2));
+
+        // Too many pixels
+        assert!(!cb.scale_up(FrameSize::MAX));
+        assert!(!cb.scale_up(usize::MAX));
+        assert_eq!(pixels(&cb), [(5, 5, 'a'), (5, 6, 'b')]);
+
+        // Majority vote (empty pixels count as a vote for transparency)
+        let mut cb = clipboard(&[(5, 5, 'a'), (5, 6, 'b'), (6, 5, 'b'), (5, 7, 'c')]);
+        cb.scale_down(2);
+        assert_eq!(pixels(&cb), [(5, 5, 'b')]);
+    }
+
+    #[test]
+    fn text_conversion() {
+        let cursor = TextPosition { row: 5, col: 5 };
+        let fg_chars = ['.', 'a', 'b', 'c', '⬜', '🟨'].into_iter().collect();
+        let cb = Clipboard::from_text("ab\n.c\n", cursor, '.', &fg_chars).expect("non empty");
+        assert_eq!(pixels(&cb), [(5, 5, 'a'), (5, 6, 'b'), (6, 6, 'c')]);
+        assert_eq!(cb.to_text('.'), "ab\n.c\n");
+
+        // Chars outside of the palette are dropped
+        let cb = Clipboard::from_text("ax\n", cursor, '.', &fg_chars).expect("non empty");
+        assert_eq!(pixels(&cb), [(5, 5, 'a')]);
+        assert!(Clipboard::from_text("xy\n", cursor, '.', &fg_chars).is_none());
+
+        let cb = Clipboard::from_text("🟨⬜\n⬜🟨", cursor, '⬜', &fg_chars).expect("non empty");
+        assert_eq!(pixels(&cb), [(5, 5, '🟨'), (6, 6, '🟨')]);
+        assert_eq!(cb.to_text('⬜'), "🟨⬜\n⬜🟨\n");
+    }
+
+    #[test]
+    fn tiles() {
+        let mut cb = clipboard(&[(5, 5, 'a'), (6, 6, 'b')]);
+        cb.cursor = TextPosition { row: 1, col: 1 };
+        assert_eq!(cb.bounding_size(), (2, 2));
+        let tiled = |row, col| cb.get_tiled(TextPosition { row, col });
+        assert_eq!(tiled(1, 1), Some('a'));
+        assert_eq!(tiled(1, 2), None);
+        assert_eq!(tiled(0, 0), Some('b'));
+        assert_eq!(tiled(3, 5), Some('a'));
+        assert_eq!(tiled(4, 6), Some('b'));
+    }
+
+    #[test]
+    fn opaque_paste() {
+        let cb = clipboard(&[(5, 5, 'a'), (6, 6, 'b')]);
+        assert_eq!(cb.paste_pixels(PasteMode::Transparent, '.').len(), 2);
+        let mut pixels = cb.paste_pixels(PasteMode::Opaque, '.');
+        pixels.sort();
+        let pos = |row, col| TextPosition { row, col };
+        assert_eq!(
+            pixels,
+            [
+                (pos(5, 5), 'a'),
+                (pos(5, 6), '.'),
+                (pos(6, 5), '.'),
+                (pos(6, 6), 'b')
+            ]
+        );
+        assert_eq!(
+            cb.get_with_mode(pos(5, 6), PasteMode::Opaque, '.'),
+            Some('.')
+        );
+        assert_eq!(cb.get_with_mode(pos(5, 7), PasteMode::Opaque, '.'), None);
+        assert_eq!(
+            cb.get_with_mode(pos(5, 6), PasteMode::Transparent, '.'),
+            None
+        );
+    }
 }
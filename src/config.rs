@@ -1,4 +1,4 @@
-use std::collections::BTreeMap;
+use std::{collections::BTreeMap, path::PathBuf};
 
 use crate::keybinding::KeyBindings;
 
@@ -8,6 +8,8 @@ pub struct Config {
     pub preview: FrameSize,
     // TODO: use a map to be able to switch palettes
     pub palette: Palette,
+    pub undo_journal: bool,
+    pub stamp_dir: Option<PathBuf>,
 }
 
 impl<'text, 'raw> TryFrom<nojson::RawJsonValue<'text, 'raw>> for Config {
@@ -17,11 +19,18 @@ impl<'text, 'raw> TryFrom<nojson::RawJsonValue<'text, 'raw>> for Config {
         let keybindings = value.to_member("keybindings")?.required()?;
         let preview = value.to_member("preview")?.required()?;
         let palette = value.to_member("palette")?.required()?;
+        let undo_journal = value.to_member("undo_journal")?;
+        let stamp_dir = value.to_member("stamp_dir")?;
 
         Ok(Config {
             keybindings: keybindings.try_into()?,
             preview: preview.try_into()?, // TODO: optional
             palette: palette.try_into()?,
+            undo_journal: undo_journal.map(|v| v.try_into())?.unwrap_or(false),
+            stamp_dir: stamp_dir
+                .map(|v| v.try_into())?
+                .flatten()
+                .map(|dir: String| PathBuf::from(dir)),
         })
     }
 }
@@ -34,12 +43,17 @@ impl Default for Config {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub struct FrameSize {
     pub width: usize,
     pub height: usize,
 }
 
+impl FrameSize {
+    // Larger canvases would take too much memory (and time to render)
+    pub const MAX: usize = 1024;
+}
+
 impl std::str::FromStr for FrameSize {
     type Err = String;
 
@@ -53,6 +67,14 @@ impl std::str::FromStr for FrameSize {
         let height = height
             .parse()
             .map_err(|e| format!("Invalid height '{}': {}", height, e))?;
+        for (name, value) in [("width", width), ("height", height)] {
+            if !(1..=Self::MAX).contains(&value) {
+                return Err(format!(
+                    "Invalid {name} '{value}': must be between 1 and {}",
+                    Self::MAX
+                ));
+            }
+        }
         Ok(FrameSize { width, height })
     }
 }
@@ -101,6 +123,13 @@ impl Color {
     pub const fn rgba(r: u8, g: u8, b: u8, a: u8) -> Self {
         Self { r, g, b, a }
     }
+
+    // Euclidean distance in the RGB space (alpha is ignored)
+    pub fn distance(self, other: Self) -> u32 {
+        let d = |a: u8, b: u8| (i32::from(a) - i32::from(b)).pow(2) as u32;
+        let squared = d(self.r, other.r) + d(self.g, other.g) + d(self.b, other.b);
+        f64::from(squared).sqrt().round() as u32
+    }
 }
 
 impl<'text, 'raw> TryFrom<nojson::RawJsonValue<'text, 'raw>> for Color {
@@ -1,13 +1,20 @@
-use std::path::PathBuf;
+use std::{collections::BTreeSet, path::PathBuf};
 
 use orfail::OrFail;
 
 use crate::{
+    brush::Brush,
     buffer::{TextBuffer, TextPosition},
-    clipboard::Clipboard,
-    config::Config,
-    keybinding::KeySequence,
+    canvas::{self, Anchor, Canvas, FrameStyle},
+    clipboard::{Clipboard, PasteMode},
+    config::{Config, FrameSize},
+    history::UndoNodeId,
+    keybinding::{KeyBindingsGroup, KeySequence},
     marker::Marker,
+    selection::{Neighborhood, Selection, SelectionMorph, SelectionOp},
+    stamp::StampPicker,
+    symmetry::SymmetryMode,
+    undo_journal::UndoJournal,
 };
 
 #[derive(Debug)]
@@ -21,13 +28,23 @@ pub struct Editor {
     pub config: Config,
     pub pending_keys: KeySequence,
     pub marker: Option<Marker>,
+    pub selection: Selection,
+    pub brush: Brush,
+    pub symmetry: SymmetryMode,
+    // Center of symmetry in half-pixel units (`None` means the center of the current canvas)
+    pub symmetry_axis: Option<TextPosition>,
     pub clipboard: Option<Clipboard>,
+    pub paste_mode: PasteMode,
+    pub undo_tree_cursor: Option<UndoNodeId>,
+    pub stamp_picker: Option<StampPicker>,
+    pub undo_journal: Option<UndoJournal>,
 }
 
 impl Editor {
     pub fn new(path: PathBuf, config: Config) -> orfail::Result<Self> {
         let mut buffer = TextBuffer::new();
         buffer.filter.fg_chars = config.keybindings.fg_chars().collect();
+        let undo_journal = config.undo_journal.then(|| UndoJournal::new(&path));
 
         Ok(Self {
             path,
@@ -42,7 +59,15 @@ impl Editor {
             config,
             pending_keys: KeySequence::default(),
             marker: None,
+            selection: Selection::default(),
+            brush: Brush::default(),
+            symmetry: SymmetryMode::default(),
+            symmetry_axis: None,
             clipboard: None,
+            paste_mode: PasteMode::default(),
+            undo_tree_cursor: None,
+            stamp_picker: None,
+            undo_journal,
         })
     }
 
@@ -57,30 +82,318 @@ impl Editor {
         }
     }
 
+    // Root key bindings group of the current mode
+    pub fn keybindings_group(&self) -> &KeyBindingsGroup {
+        let keybindings = &self.config.keybindings;
+        if self.undo_tree_cursor.is_some() {
+            &keybindings.undo_tree
+        } else if self.stamp_picker.is_some() {
+            &keybindings.stamp_picker
+        } else if self.clipboard.is_some() {
+            &keybindings.clipboard
+        } else {
+            &keybindings.main
+        }
+    }
+
+    pub fn canvases(&self) -> &[Canvas] {
+        self.buffer.canvases()
+    }
+
+    // The canvas that contains the cursor
+    pub fn current_canvas(&self) -> Option<Canvas> {
+        self.canvases()
+            .iter()
+            .copied()
+            .find(|canvas| canvas.contains(self.cursor))
+    }
+
+    // Moves the cursor while keeping it inside of the current canvas (if any)
+    // as long as a marker or the clipboard is being placed
+    pub fn move_cursor(&mut self, pos: TextPosition) {
+        let pos = match self.current_canvas() {
+            Some(canvas) if self.marker.is_some() || self.clipboard.is_some() => canvas.clamp(pos),
+            _ => pos,
+        };
+        self.cursor = pos;
+        self.dirty.render = true;
+        if let Some(mut marker) = self.marker.take() {
+            marker.handle_cursor_move(self);
+            self.marker = Some(marker);
+        }
+        if let Some(cb) = &mut self.clipboard {
+            cb.cursor = self.buffer.to_pixel_position(self.cursor);
+        }
+    }
+
+    // Cursor position in pixel units (see `TextBuffer::to_pixel_position()`)
+    pub fn pixel_cursor(&self) -> TextPosition {
+        self.buffer.to_pixel_position(self.cursor)
+    }
+
+    pub fn char_at_pixel(&self, pixel: TextPosition) -> Option<char> {
+        self.buffer.get_char_at(self.buffer.to_text_position(pixel))
+    }
+
+    // Marked positions of the current marker (clamped to the current canvas, frames excluded)
+    pub fn marked_positions(&self) -> Vec<TextPosition> {
+        self.marker
+            .as_ref()
+            .map_or_else(Vec::new, |marker| self.marker_positions(marker))
+    }
+
+    pub fn marker_positions(&self, marker: &Marker) -> Vec<TextPosition> {
+        let pixels = if marker.uses_brush() {
+            self.brush.apply(marker.marked_positions())
+        } else {
+            marker.marked_positions().collect()
+        };
+        self.clamp_pixels(self.mirror_pixels(pixels.into_iter()).into_iter())
+    }
+
+    // Center of symmetry in half-pixel units
+    pub fn symmetry_center(&self) -> Option<TextPosition> {
+        if let Some(axis) = self.symmetry_axis {
+            return Some(axis);
+        }
+        let canvas = self.current_canvas()?;
+        let top_left = self.buffer.to_pixel_position(canvas.top_left);
+        // Rows may have different pixel counts, so the widest one decides the horizontal center
+        let width = canvas
+            .pixels(&self.buffer)
+            .iter()
+            .map(|row| row.len())
+            .max()
+            .unwrap_or(0);
+        Some(TextPosition {
+            row: canvas.top_left.row + canvas.bottom_right.row,
+            col: (2 * top_left.col + width).saturating_sub(1),
+        })
+    }
+
+    // Adds the mirrored copies of the pixels according to the symmetry mode
+    fn mirror_pixels(&self, pixels: impl Iterator<Item = TextPosition>) -> Vec<TextPosition> {
+        match self.symmetry_center() {
+            Some(center) if self.symmetry != SymmetryMode::Off => pixels
+                .flat_map(|pixel| self.symmetry.mirror(pixel, center))
+                .collect(),
+            _ => pixels.collect(),
+        }
+    }
+
+    // Converts pixels to text positions within the current canvas (frames excluded)
+    fn clamp_pixels(&self, pixels: impl Iterator<Item = TextPosition>) -> Vec<TextPosition> {
+        let canvases = self.canvases();
+        let canvas = canvases.iter().find(|c| c.contains(self.cursor));
+        pixels
+            .map(|pixel| self.buffer.to_text_position(pixel))
+            .filter(|pos| canvas.is_none_or(|canvas| canvas.contains(*pos)))
+            .filter(|pos| !canvases.iter().any(|c| c.is_on_frame(*pos)))
+            .collect()
+    }
+
+    // Same as `TextBuffer::update_bulk()` except that canvas frames are never overwritten
+    pub fn update_bulk(&mut self, updates: impl Iterator<Item = (TextPosition, char)>) -> bool {
+        let canvases = self.canvases().to_vec();
+        let updates = updates.filter(|(pos, _)| !canvases.iter().any(|c| c.is_on_frame(*pos)));
+        if !self.buffer.update_bulk(updates) {
+            return false;
+        }
+        self.dirty.content = true;
+        self.dirty.render = true;
+        true
+    }
+
+    // Same as `update_bulk()` except that positions are in pixel units
+    pub fn update_pixels(&mut self, updates: impl Iterator<Item = (TextPosition, char)>) -> bool {
+        let updates: Vec<_> = updates
+            .map(|(pixel, c)| (self.buffer.to_text_position(pixel), c))
+            .collect();
+        self.update_bulk(updates.into_iter())
+    }
+
+    // Union of the selection and the marked positions
+    pub fn selected_positions(&self) -> Vec<TextPosition> {
+        let mut positions = self.marked_positions();
+        positions.extend(
+            self.selection
+                .positions()
+                .map(|pixel| self.buffer.to_text_position(pixel)),
+        );
+        positions.sort();
+        positions.dedup();
+        positions
+    }
+
+    // Combines the text positions with the selection
+    pub fn select(&mut self, op: SelectionOp, positions: Vec<TextPosition>) {
+        let pixels: Vec<_> = positions
+            .into_iter()
+            .map(|pos| self.buffer.to_pixel_position(pos))
+            .collect();
+        self.selection.apply(op, pixels);
+        self.dirty.render = true;
+    }
+
+    // Replaces the selection with the morphed selected positions
+    // Returns `false` if nothing is selected (an empty result just clears the selection)
+    pub fn morph_selection(&mut self, morph: SelectionMorph, neighborhood: Neighborhood) -> bool {
+        let pixels: BTreeSet<_> = self
+            .selected_positions()
+            .into_iter()
+            .map(|pos| self.buffer.to_pixel_position(pos))
+            .collect();
+        if pixels.is_empty() {
+            return false;
+        }
+        let positions = self.clamp_pixels(morph.apply(&pixels, neighborhood).into_iter());
+        self.marker = None;
+        self.selection.clear();
+        self.select(SelectionOp::Add, positions);
+        true
+    }
+
     pub fn dot(&mut self, c: char) -> orfail::Result<()> {
-        if let Some(marker) = self.marker.take() {
-            // Handle marker: apply character to all marked positions
-            let positions: Vec<_> = marker.marked_positions().collect();
-            let updates = positions.into_iter().map(|pos| (pos, c));
-
-            if self.buffer.update_bulk(updates) {
-                self.dirty.content = true;
-                self.dirty.render = true;
-            } else {
-                self.set_message("No effect");
-            }
+        let updated = if self.marker.is_some() || !self.selection.is_empty() {
+            // Handle marker and selection: apply character to all selected positions
+            let positions = self.selected_positions();
+            self.marker = None;
+            self.update_bulk(positions.into_iter().map(|pos| (pos, c)))
         } else {
-            // Handle single character update at cursor
-            if self.buffer.update(self.cursor, c) {
-                self.dirty.content = true;
-                self.dirty.render = true;
-            } else {
-                self.set_message("No effect");
-            }
+            // Handle single character update at cursor (painted with the brush and mirrored)
+            let pixels = self.brush.apply(std::iter::once(self.pixel_cursor()));
+            let pixels = self.mirror_pixels(pixels.into_iter());
+            let positions = self.clamp_pixels(pixels.into_iter());
+            self.update_bulk(positions.into_iter().map(|pos| (pos, c)))
+        };
+        if !updated {
+            self.set_message("No effect");
         }
         Ok(())
     }
 
+    // Inserts a blank framed canvas below the cursor line (or the current canvas)
+    pub fn insert_canvas(&mut self, size: FrameSize) {
+        let mut row = match self.current_canvas() {
+            Some(canvas) => {
+                canvas
+                    .frame
+                    .map_or(canvas.bottom_right, |f| f.bottom_right)
+                    .row
+                    + 1
+            }
+            None => (self.cursor.row + 1).min(self.buffer.rows()),
+        };
+        if self
+            .buffer
+            .lines()
+            .nth(row)
+            .is_some_and(|line| line.trim_start().starts_with("```"))
+        {
+            row += 1;
+        }
+
+        let markdown = self.path.extension().is_some_and(|ext| ext == "md");
+        let canvas_char = self.config.keybindings.canvas_char();
+        let mut lines = vec![String::new()];
+        lines.extend(canvas::blank_canvas_lines(
+            size,
+            FrameStyle::Ascii,
+            canvas_char,
+            markdown,
+        ));
+        let top_left = TextPosition {
+            row: row + if markdown { 3 } else { 2 },
+            col: 1,
+        };
+        self.buffer.replace_lines(row, 0, lines);
+        self.dirty.content = true;
+
+        self.move_cursor(top_left);
+        self.set_message(format!("Inserted {}x{} canvas", size.width, size.height));
+    }
+
+    // Rewrites the pixels of the current canvas.
+    // `f` takes the pixels and the cursor position as (row, pixel) indices.
+    pub fn edit_canvas(&mut self, f: impl FnOnce(&mut Vec<Vec<char>>, usize, usize)) {
+        let Some(canvas) = self.current_canvas() else {
+            self.set_message("Not in a canvas");
+            return;
+        };
+        // Without a fence or a frame, the canvas is the whole file and may contain prose
+        if !canvas.fenced && canvas.frame.is_none() {
+            self.set_message("Not in a fenced or framed canvas");
+            return;
+        }
+
+        let mut pixels = canvas.pixels(&self.buffer);
+        let row = self.cursor.row - canvas.top_left.row;
+        let index = canvas.pixel_index(&self.buffer, self.cursor);
+        f(&mut pixels, row, index);
+        if pixels.iter().all(|row| row.is_empty()) {
+            self.set_message("Canvas cannot be empty");
+            return;
+        }
+
+        let lines = canvas.to_lines(&self.buffer, &pixels);
+        let rows = canvas.row_range();
+        if !self.buffer.replace_lines(rows.start, rows.len(), lines) {
+            self.set_message("No effect");
+            return;
+        }
+        self.dirty.content = true;
+        self.dirty.render = true;
+        self.marker = None;
+        self.selection.clear();
+
+        // Keep the cursor inside of the edited canvas
+        if let Some(canvas) = self
+            .canvases()
+            .iter()
+            .find(|c| c.top_left == canvas.top_left)
+        {
+            self.cursor = canvas.clamp(self.cursor);
+        }
+    }
+
+    pub fn insert_canvas_row(&mut self) {
+        let canvas_char = self.config.keybindings.canvas_char();
+        self.edit_canvas(|pixels, row, _| {
+            let width = pixels[row].len();
+            pixels.insert(row, vec![canvas_char; width]);
+        });
+    }
+
+    pub fn delete_canvas_row(&mut self) {
+        self.edit_canvas(|pixels, row, _| {
+            pixels.remove(row);
+        });
+    }
+
+    pub fn insert_canvas_column(&mut self) {
+        let canvas_char = self.config.keybindings.canvas_char();
+        self.edit_canvas(|pixels, _, index| {
+            for row in pixels {
+                row.insert(index.min(row.len()), canvas_char);
+            }
+        });
+    }
+
+    pub fn delete_canvas_column(&mut self) {
+        self.edit_canvas(|pixels, _, index| {
+            for row in pixels.iter_mut().filter(|row| index < row.len()) {
+                row.remove(index);
+            }
+        });
+    }
+
+    pub fn resize_canvas(&mut self, size: FrameSize, anchor: Anchor) {
+        let canvas_char = self.config.keybindings.canvas_char();
+        self.edit_canvas(|pixels, _, _| {
+            canvas::resize_pixels(pixels, size, anchor, canvas_char);
+        });
+    }
+
     pub fn save(&mut self) -> orfail::Result<()> {
         if !self.dirty.content {
             self.set_message("No changes to save");
@@ -89,7 +402,10 @@ impl Editor {
 
         let mut content = self.buffer.lines().collect::<Vec<&str>>().join("\n");
         content.push('\n');
-        std::fs::write(&self.path, content).or_fail()?;
+        std::fs::write(&self.path, &content).or_fail()?;
+        if let Some(journal) = &self.undo_journal {
+            journal.save(&content, self.buffer.history()).or_fail()?;
+        }
 
         self.dirty.content = false;
         self.set_message(format!("Saved {}", self.path.display()));
@@ -99,8 +415,27 @@ impl Editor {
 
     pub fn reload(&mut self) -> orfail::Result<()> {
         let text = std::fs::read_to_string(&self.path).or_fail()?;
+        // A broken journal only costs the undo history, the file itself is still opened
+        let (history, journal_error) = match self.undo_journal.as_ref().map(|j| j.load(&text)) {
+            Some(Ok(history)) => (history, None),
+            Some(Err(e)) => (None, Some(e.message)),
+            None => (None, None),
+        };
         self.buffer.set_text(text);
-        self.set_message(format!("Loaded {}", self.path.display()));
+        if let Some(history) = history {
+            self.buffer.set_history(history);
+            self.set_message(format!(
+                "Loaded {} (undo history restored)",
+                self.path.display()
+            ));
+        } else if let Some(e) = journal_error {
+            self.set_message(format!(
+                "Loaded {} (undo history discarded: {e})",
+                self.path.display()
+            ));
+        } else {
+            self.set_message(format!("Loaded {}", self.path.display()));
+        }
 
         self.dirty.content = false;
         self.dirty.render = true;
@@ -1,3 +1,13 @@
+use crate::{
+    brush::{Brush, BrushKind},
+    canvas::Anchor,
+    clipboard::Clipboard,
+    config::FrameSize,
+    marker::FillMode,
+    selection::{Neighborhood, SelectionMorph, SelectionOp},
+    symmetry::SymmetryMode,
+};
+
 // AppCommand?
 #[derive(Debug, Clone)]
 pub enum EditorCommand {
@@ -8,6 +18,11 @@ pub enum EditorCommand {
     Cancel,
     Save,
     Undo,
+    Redo,
+    UndoTree,
+    UndoTreeJump,
+    Stamps,
+    StampSelect,
     // reload
     // call or execute or shell
     Scope(String),
@@ -15,15 +30,51 @@ pub enum EditorCommand {
     NextLine,
     PrevChar,
     NextChar,
+    PrevCanvas,
+    NextCanvas,
+    NewCanvas(FrameSize),
+    ResizeCanvas(FrameSize, Anchor),
+    InsertRow,
+    DeleteRow,
+    InsertColumn,
+    DeleteColumn,
     Dot(char),
-    MarkStroke,
+    MarkStroke { pixel_perfect: bool },
     MarkLine,
     MarkRect,
     MarkFilledRect,
-    MarkFill,
+    MarkFill(FillMode),
+    MarkEllipse { circle: bool },
+    MarkFilledEllipse { circle: bool },
+    MarkPolyline,
+    MarkCurve,
+    MarkFilledPolygon,
+    CommitVertex,
+    ClosePolygon,
+    Select(SelectionOp),
+    SelectWand { mode: FillMode, tolerance: u32 },
+    MorphSelection(SelectionMorph, Neighborhood),
+    InvertSelection,
+    ClearSelection,
+    Brush(BrushKind),
+    BrushSize(usize),
+    Symmetry(SymmetryMode),
+    SymmetryAxis { center: bool },
     Cut,
     Copy,
     Paste,
+    PasteTile,
+    PasteRepeat,
+    TogglePasteMode,
+    Yank,
+    YankTo(String),
+    PasteFrom(String),
+    RotateCw,
+    RotateCcw,
+    FlipHorizontal,
+    FlipVertical,
+    ScaleUp(usize),
+    ScaleDown(usize),
 }
 
 impl std::fmt::Display for EditorCommand {
@@ -36,20 +87,94 @@ impl std::fmt::Display for EditorCommand {
             EditorCommand::Cancel => write!(f, "cancel"),
             EditorCommand::Save => write!(f, "save"),
             EditorCommand::Undo => write!(f, "undo"),
+            EditorCommand::Redo => write!(f, "redo"),
+            EditorCommand::UndoTree => write!(f, "undo-tree"),
+            EditorCommand::UndoTreeJump => write!(f, "undo-tree-jump"),
+            EditorCommand::Stamps => write!(f, "stamps"),
+            EditorCommand::StampSelect => write!(f, "stamp-select"),
             EditorCommand::Scope(s) => write!(f, "scope({})", s),
             EditorCommand::PrevLine => write!(f, "prev-line"),
             EditorCommand::NextLine => write!(f, "next-line"),
             EditorCommand::PrevChar => write!(f, "prev-char"),
             EditorCommand::NextChar => write!(f, "next-char"),
+            EditorCommand::PrevCanvas => write!(f, "prev-canvas"),
+            EditorCommand::NextCanvas => write!(f, "next-canvas"),
+            EditorCommand::NewCanvas(size) => {
+                write!(f, "new-canvas({}x{})", size.width, size.height)
+            }
+            EditorCommand::ResizeCanvas(size, anchor) => {
+                write!(
+                    f,
+                    "resize-canvas({}x{},{})",
+                    size.width, size.height, anchor
+                )
+            }
+            EditorCommand::InsertRow => write!(f, "insert-row"),
+            EditorCommand::DeleteRow => write!(f, "delete-row"),
+            EditorCommand::InsertColumn => write!(f, "insert-column"),
+            EditorCommand::DeleteColumn => write!(f, "delete-column"),
             EditorCommand::Dot(c) => write!(f, "dot({})", c),
-            EditorCommand::MarkStroke => write!(f, "mark-stroke"),
+            EditorCommand::MarkStroke {
+                pixel_perfect: false,
+            } => write!(f, "mark-stroke"),
+            EditorCommand::MarkStroke {
+                pixel_perfect: true,
+            } => write!(f, "mark-stroke(pixel-perfect)"),
             EditorCommand::MarkLine => write!(f, "mark-line"),
             EditorCommand::MarkRect => write!(f, "mark-rect"),
             EditorCommand::MarkFilledRect => write!(f, "mark-filled-rect"),
-            EditorCommand::MarkFill => write!(f, "mark-fill"),
+            EditorCommand::MarkFill(FillMode::Connected4) => write!(f, "mark-fill"),
+            EditorCommand::MarkFill(mode) => write!(f, "mark-fill({mode})"),
+            EditorCommand::MarkPolyline => write!(f, "mark-polyline"),
+            EditorCommand::MarkCurve => write!(f, "mark-curve"),
+            EditorCommand::MarkFilledPolygon => write!(f, "mark-filled-polygon"),
+            EditorCommand::CommitVertex => write!(f, "commit-vertex"),
+            EditorCommand::ClosePolygon => write!(f, "close-polygon"),
+            EditorCommand::MarkEllipse { circle: false } => write!(f, "mark-ellipse"),
+            EditorCommand::MarkEllipse { circle: true } => write!(f, "mark-ellipse(circle)"),
+            EditorCommand::MarkFilledEllipse { circle: false } => write!(f, "mark-filled-ellipse"),
+            EditorCommand::MarkFilledEllipse { circle: true } => {
+                write!(f, "mark-filled-ellipse(circle)")
+            }
+            EditorCommand::Select(op) => write!(f, "select({op})"),
+            EditorCommand::SelectWand {
+                mode: FillMode::Connected4,
+                tolerance: 0,
+            } => write!(f, "select-wand"),
+            EditorCommand::SelectWand { mode, tolerance: 0 } => write!(f, "select-wand({mode})"),
+            EditorCommand::SelectWand { mode, tolerance } => {
+                write!(f, "select-wand({mode},{tolerance})")
+            }
+            EditorCommand::MorphSelection(morph, Neighborhood::Four) => {
+                write!(f, "morph-selection({morph})")
+            }
+            EditorCommand::MorphSelection(morph, neighborhood) => {
+                write!(f, "morph-selection({morph},{neighborhood})")
+            }
+            EditorCommand::InvertSelection => write!(f, "invert-selection"),
+            EditorCommand::ClearSelection => write!(f, "clear-selection"),
+            EditorCommand::Brush(kind) => write!(f, "brush({kind})"),
+            EditorCommand::BrushSize(size) => write!(f, "brush-size({size})"),
+            EditorCommand::Symmetry(mode) => write!(f, "symmetry({mode})"),
+            EditorCommand::SymmetryAxis { center: false } => write!(f, "symmetry-axis"),
+            EditorCommand::SymmetryAxis { center: true } => write!(f, "symmetry-axis(center)"),
             EditorCommand::Cut => write!(f, "cut"),
             EditorCommand::Copy => write!(f, "copy"),
             EditorCommand::Paste => write!(f, "paste"),
+            EditorCommand::PasteTile => write!(f, "paste-tile"),
+            EditorCommand::PasteRepeat => write!(f, "paste-repeat"),
+            EditorCommand::TogglePasteMode => write!(f, "toggle-paste-mode"),
+            EditorCommand::Yank => write!(f, "yank"),
+            EditorCommand::YankTo(name) => write!(f, "yank-to({name})"),
+            EditorCommand::PasteFrom(name) => write!(f, "paste-from({name})"),
+            EditorCommand::RotateCw => write!(f, "rotate-cw"),
+            EditorCommand::RotateCcw => write!(f, "rotate-ccw"),
+            EditorCommand::FlipHorizontal => write!(f, "flip-horizontal"),
+            EditorCommand::FlipVertical => write!(f, "flip-vertical"),
+            EditorCommand::ScaleUp(2) => write!(f, "scale-up"),
+            EditorCommand::ScaleUp(factor) => write!(f, "scale-up({factor})"),
+            EditorCommand::ScaleDown(2) => write!(f, "scale-down"),
+            EditorCommand::ScaleDown(factor) => write!(f, "scale-down({factor})"),
         }
     }
 }
@@ -65,18 +190,61 @@ impl std::str::FromStr for EditorCommand {
             "cancel" => Ok(EditorCommand::Cancel),
             "save" => Ok(EditorCommand::Save),
             "undo" => Ok(EditorCommand::Undo),
+            "redo" => Ok(EditorCommand::Redo),
+            "undo-tree" => Ok(EditorCommand::UndoTree),
+            "undo-tree-jump" => Ok(EditorCommand::UndoTreeJump),
+            "stamps" => Ok(EditorCommand::Stamps),
+            "stamp-select" => Ok(EditorCommand::StampSelect),
             "prev-line" => Ok(EditorCommand::PrevLine),
             "next-line" => Ok(EditorCommand::NextLine),
             "prev-char" => Ok(EditorCommand::PrevChar),
             "next-char" => Ok(EditorCommand::NextChar),
-            "mark-stroke" => Ok(EditorCommand::MarkStroke),
+            "prev-canvas" => Ok(EditorCommand::PrevCanvas),
+            "next-canvas" => Ok(EditorCommand::NextCanvas),
+            "insert-row" => Ok(EditorCommand::InsertRow),
+            "delete-row" => Ok(EditorCommand::DeleteRow),
+            "insert-column" => Ok(EditorCommand::InsertColumn),
+            "delete-column" => Ok(EditorCommand::DeleteColumn),
+            "mark-stroke" => Ok(EditorCommand::MarkStroke {
+                pixel_perfect: false,
+            }),
+            "mark-stroke(pixel-perfect)" => Ok(EditorCommand::MarkStroke {
+                pixel_perfect: true,
+            }),
             "mark-line" => Ok(EditorCommand::MarkLine),
             "mark-rect" => Ok(EditorCommand::MarkRect),
             "mark-filled-rect" => Ok(EditorCommand::MarkFilledRect),
-            "mark-fill" => Ok(EditorCommand::MarkFill),
+            "mark-fill" => Ok(EditorCommand::MarkFill(FillMode::Connected4)),
+            "mark-polyline" => Ok(EditorCommand::MarkPolyline),
+            "mark-curve" => Ok(EditorCommand::MarkCurve),
+            "mark-filled-polygon" => Ok(EditorCommand::MarkFilledPolygon),
+            "commit-vertex" => Ok(EditorCommand::CommitVertex),
+            "close-polygon" => Ok(EditorCommand::ClosePolygon),
+            "mark-ellipse" => Ok(EditorCommand::MarkEllipse { circle: false }),
+            "mark-ellipse(circle)" => Ok(EditorCommand::MarkEllipse { circle: true }),
+            "mark-filled-ellipse" => Ok(EditorCommand::MarkFilledEllipse { circle: false }),
+            "mark-filled-ellipse(circle)" => Ok(EditorCommand::MarkFilledEllipse { circle: true }),
+            "select-wand" => Ok(EditorCommand::SelectWand {
+                mode: FillMode::Connected4,
+                tolerance: 0,
+            }),
+            "invert-selection" => Ok(EditorCommand::InvertSelection),
+            "clear-selection" => Ok(EditorCommand::ClearSelection),
+            "symmetry-axis" => Ok(EditorCommand::SymmetryAxis { center: false }),
+            "symmetry-axis(center)" => Ok(EditorCommand::SymmetryAxis { center: true }),
             "cut" => Ok(EditorCommand::Cut),
             "copy" => Ok(EditorCommand::Copy),
             "paste" => Ok(EditorCommand::Paste),
+            "paste-tile" => Ok(EditorCommand::PasteTile),
+            "paste-repeat" => Ok(EditorCommand::PasteRepeat),
+            "toggle-paste-mode" => Ok(EditorCommand::TogglePasteMode),
+            "yank" => Ok(EditorCommand::Yank),
+            "rotate-cw" => Ok(EditorCommand::RotateCw),
+            "rotate-ccw" => Ok(EditorCommand::RotateCcw),
+            "flip-horizontal" => Ok(EditorCommand::FlipHorizontal),
+            "flip-vertical" => Ok(EditorCommand::FlipVertical),
+            "scale-up" => Ok(EditorCommand::ScaleUp(2)),
+            "scale-down" => Ok(EditorCommand::ScaleDown(2)),
             s if s.starts_with("dot(") && s.ends_with(")") => {
                 let arg = &s[4..s.len() - 1];
                 let mut chars = arg.chars();
@@ -93,6 +261,109 @@ impl std::str::FromStr for EditorCommand {
                     _ => Err(format!("invalid bg command: {}", s)),
                 }
             }
+            s if s.starts_with("new-canvas(") && s.ends_with(")") => {
+                let size = s[11..s.len() - 1]
+                    .parse()
+                    .map_err(|e| format!("invalid new-canvas command: {}: {}", s, e))?;
+                Ok(EditorCommand::NewCanvas(size))
+            }
+            s if s.starts_with("resize-canvas(") && s.ends_with(")") => {
+                let args = &s[14..s.len() - 1];
+                let (size, anchor) = args.split_once(',').unwrap_or((args, "top-left"));
+                let size = size
+                    .parse()
+                    .map_err(|e| format!("invalid resize-canvas command: {}: {}", s, e))?;
+                let anchor = anchor
+                    .parse()
+                    .map_err(|e| format!("invalid resize-canvas command: {}: {}", s, e))?;
+                Ok(EditorCommand::ResizeCanvas(size, anchor))
+            }
+            s if s.starts_with("scale-up(") && s.ends_with(")") => {
+                match s[9..s.len() - 1].parse() {
+                    Ok(factor) if (2..=Clipboard::MAX_SCALE_FACTOR).contains(&factor) => {
+                        Ok(EditorCommand::ScaleUp(factor))
+                    }
+                    _ => Err(format!("invalid scale-up command: {}", s)),
+                }
+            }
+            s if s.starts_with("scale-down(") && s.ends_with(")") => {
+                match s[11..s.len() - 1].parse() {
+                    Ok(factor) if (2..=Clipboard::MAX_SCALE_FACTOR).contains(&factor) => {
+                        Ok(EditorCommand::ScaleDown(factor))
+                    }
+                    _ => Err(format!("invalid scale-down command: {}", s)),
+                }
+            }
+            s if s.starts_with("yank-to(") && s.ends_with(")") => {
+                let name = &s[8..s.len() - 1];
+                if name.is_empty() {
+                    Err(format!("invalid yank-to command: {}", s))
+                } else {
+                    Ok(EditorCommand::YankTo(name.to_owned()))
+                }
+            }
+            s if s.starts_with("paste-from(") && s.ends_with(")") => {
+                let name = &s[11..s.len() - 1];
+                if name.is_empty() {
+                    Err(format!("invalid paste-from command: {}", s))
+                } else {
+                    Ok(EditorCommand::PasteFrom(name.to_owned()))
+                }
+            }
+            s if s.starts_with("mark-fill(") && s.ends_with(")") => {
+                let mode = s[10..s.len() - 1]
+                    .parse()
+                    .map_err(|e| format!("invalid mark-fill command: {}: {}", s, e))?;
+                Ok(EditorCommand::MarkFill(mode))
+            }
+            s if s.starts_with("select(") && s.ends_with(")") => {
+                let op = s[7..s.len() - 1]
+                    .parse()
+                    .map_err(|e| format!("invalid select command: {}: {}", s, e))?;
+                Ok(EditorCommand::Select(op))
+            }
+            s if s.starts_with("select-wand(") && s.ends_with(")") => {
+                let args = &s[12..s.len() - 1];
+                let (mode, tolerance) = args.split_once(',').unwrap_or((args, "0"));
+                let mode = mode
+                    .parse()
+                    .map_err(|e| format!("invalid select-wand command: {}: {}", s, e))?;
+                let tolerance = tolerance
+                    .parse()
+                    .map_err(|e| format!("invalid select-wand command: {}: {}", s, e))?;
+                Ok(EditorCommand::SelectWand { mode, tolerance })
+            }
+            s if s.starts_with("morph-selection(") && s.ends_with(")") => {
+                let args = &s[16..s.len() - 1];
+                let (morph, neighborhood) = args.split_once(',').unwrap_or((args, "4"));
+                let morph = morph
+                    .parse()
+                    .map_err(|e| format!("invalid morph-selection command: {}: {}", s, e))?;
+                let neighborhood = neighborhood
+                    .parse()
+                    .map_err(|e| format!("invalid morph-selection command: {}: {}", s, e))?;
+                Ok(EditorCommand::MorphSelection(morph, neighborhood))
+            }
+            s if s.starts_with("brush(") && s.ends_with(")") => {
+                let kind = s[6..s.len() - 1]
+                    .parse()
+                    .map_err(|e| format!("invalid brush command: {}: {}", s, e))?;
+                Ok(EditorCommand::Brush(kind))
+            }
+            s if s.starts_with("brush-size(") && s.ends_with(")") => {
+                match s[11..s.len() - 1].parse() {
+                    Ok(size) if (1..=Brush::MAX_SIZE).contains(&size) => {
+                        Ok(EditorCommand::BrushSize(size))
+                    }
+                    _ => Err(format!("invalid brush-size command: {}", s)),
+                }
+            }
+            s if s.starts_with("symmetry(") && s.ends_with(")") => {
+                let mode = s[9..s.len() - 1]
+                    .parse()
+                    .map_err(|e| format!("invalid symmetry command: {}: {}", s, e))?;
+                Ok(EditorCommand::Symmetry(mode))
+            }
             s if s.starts_with("scope(") && s.ends_with(")") => {
                 let group_name = &s[6..s.len() - 1];
                 if group_name.is_empty() {
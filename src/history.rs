@@ -0,0 +1,385 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::buffer::TextPosition;
+
+pub type UndoNodeId = usize;
+
+// Undo tree: undoing an edit and then making a new one adds a sibling branch instead of
+// discarding the undone edits.
+#[derive(Debug, Clone)]
+pub struct UndoHistory {
+    nodes: Vec<UndoNode>,
+    current: UndoNodeId,
+}
+
+impl UndoHistory {
+    pub const ROOT: UndoNodeId = 0;
+
+    pub fn current(&self) -> UndoNodeId {
+        self.current
+    }
+
+    pub fn node(&self, id: UndoNodeId) -> Option<&UndoNode> {
+        self.nodes.get(id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.len() == 1
+    }
+
+    // Returns `(node_id, depth)` pairs in depth-first order
+    pub fn tree_order(&self) -> Vec<(UndoNodeId, usize)> {
+        // Children are in ascending order since they are pushed after their parents
+        let mut children = vec![Vec::new(); self.nodes.len()];
+        for (id, node) in self.nodes.iter().enumerate() {
+            if let Some(parent) = node.parent {
+                children[parent].push(id);
+            }
+        }
+
+        let mut order = Vec::with_capacity(self.nodes.len());
+        let mut stack = vec![(Self::ROOT, 0)];
+        while let Some((id, depth)) = stack.pop() {
+            order.push((id, depth));
+            for &child in children[id].iter().rev() {
+                stack.push((child, depth + 1));
+            }
+        }
+        order
+    }
+
+    pub fn depth(&self, mut id: UndoNodeId) -> usize {
+        let mut depth = 0;
+        while let Some(parent) = self.nodes[id].parent {
+            id = parent;
+            depth += 1;
+        }
+        depth
+    }
+
+    // Number of nodes that `redo` can step through from the current node
+    pub fn redo_count(&self) -> usize {
+        let mut count = 0;
+        let mut id = self.current;
+        while let Some(child) = self.nodes[id].last_child {
+            id = child;
+            count += 1;
+        }
+        count
+    }
+
+    pub(crate) fn push(&mut self, operation: UndoOperation) {
+        let id = self.nodes.len();
+        self.nodes.push(UndoNode {
+            parent: Some(self.current),
+            last_child: None,
+            timestamp: SystemTime::now(),
+            operation,
+        });
+        self.nodes[self.current].last_child = Some(id);
+        self.current = id;
+    }
+
+    // Moves to the parent node and returns the operation to revert
+    pub(crate) fn undo(&mut self) -> Option<&UndoOperation> {
+        let id = self.current;
+        let parent = self.nodes[id].parent?;
+        self.nodes[parent].last_child = Some(id);
+        self.current = parent;
+        Some(&self.nodes[id].operation)
+    }
+
+    // Moves to the most recently visited child node and returns the operation to apply
+    pub(crate) fn redo(&mut self) -> Option<&UndoOperation> {
+        let child = self.nodes[self.current].last_child?;
+        self.current = child;
+        Some(&self.nodes[child].operation)
+    }
+
+    // Returns the nodes to undo (from `from`) and to redo (towards `to`) in traversal order
+    pub(crate) fn path(
+        &self,
+        from: UndoNodeId,
+        to: UndoNodeId,
+    ) -> (Vec<UndoNodeId>, Vec<UndoNodeId>) {
+        let ancestors = |mut id: UndoNodeId| {
+            let mut ids = vec![id];
+            while let Some(parent) = self.nodes[id].parent {
+                ids.push(parent);
+                id = parent;
+            }
+            ids
+        };
+        let mut ups = ancestors(from);
+        let mut downs = ancestors(to);
+
+        // Drop the common ancestors
+        while ups.last().is_some() && ups.last() == downs.last() {
+            ups.pop();
+            downs.pop();
+        }
+        downs.reverse();
+        (ups, downs)
+    }
+
+    // Whether every `ReplaceLines` operation fits in the lines it is applied to,
+    // given that the text of the current node has `rows` lines (e.g., for a loaded journal)
+    pub(crate) fn fits_rows(&self, rows: usize) -> bool {
+        // Splicing `len` lines at `row` of `rows` lines, returns the resulting number of lines
+        let splice = |rows: usize, row: usize, len: usize, new_len: usize| {
+            row.checked_add(len)
+                .filter(|&end| end <= rows)
+                .map(|_| rows - len + new_len)
+        };
+
+        let mut root_rows = rows;
+        let mut id = self.current;
+        while let Some(parent) = self.nodes[id].parent {
+            if let UndoOperation::ReplaceLines {
+                row,
+                old_lines,
+                new_lines,
+            } = &self.nodes[id].operation
+            {
+                let Some(rows) = splice(root_rows, *row, new_lines.len(), old_lines.len()) else {
+                    return false;
+                };
+                root_rows = rows;
+            }
+            id = parent;
+        }
+
+        // Parents precede their children
+        let mut node_rows = vec![root_rows; self.nodes.len()];
+        for (id, node) in self.nodes.iter().enumerate().skip(1) {
+            let rows = node_rows[node.parent.expect("bug")];
+            node_rows[id] = match &node.operation {
+                UndoOperation::BulkUpdate { .. } => rows,
+                UndoOperation::ReplaceLines {
+                    row,
+                    old_lines,
+                    new_lines,
+                } => match splice(rows, *row, old_lines.len(), new_lines.len()) {
+                    Some(rows) => rows,
+                    None => return false,
+                },
+            };
+        }
+        true
+    }
+
+    pub(crate) fn set_current(&mut self, id: UndoNodeId) {
+        let mut child = id;
+        while let Some(parent) = self.nodes[child].parent {
+            self.nodes[parent].last_child = Some(child);
+            child = parent;
+        }
+        self.current = id;
+    }
+}
+
+impl Default for UndoHistory {
+    fn default() -> Self {
+        Self {
+            nodes: vec![UndoNode {
+                parent: None,
+                last_child: None,
+                timestamp: SystemTime::now(),
+                operation: UndoOperation::BulkUpdate {
+                    updates: Vec::new(),
+                },
+            }],
+            current: Self::ROOT,
+        }
+    }
+}
+
+impl nojson::DisplayJson for UndoHistory {
+    fn fmt(&self, f: &mut nojson::JsonFormatter<'_, '_>) -> std::fmt::Result {
+        f.object(|f| {
+            f.member("current", self.current)?;
+            f.member("nodes", &self.nodes)
+        })
+    }
+}
+
+impl<'text, 'raw> TryFrom<nojson::RawJsonValue<'text, 'raw>> for UndoHistory {
+    type Error = nojson::JsonParseError;
+
+    fn try_from(value: nojson::RawJsonValue<'text, 'raw>) -> Result<Self, Self::Error> {
+        let raw_current = value.to_member("current")?.required()?;
+        let raw_nodes = value.to_member("nodes")?.required()?;
+        let current: UndoNodeId = raw_current.try_into()?;
+        let nodes: Vec<UndoNode> = raw_nodes.try_into()?;
+        if current >= nodes.len() {
+            return Err(raw_current.invalid("no such node"));
+        }
+        for (id, node) in nodes.iter().enumerate() {
+            if node.parent.is_none_or(|parent| parent >= id) != (id == Self::ROOT)
+                || node.last_child.is_some_and(|child| {
+                    nodes
+                        .get(child)
+                        .is_none_or(|child| child.parent != Some(id))
+                })
+            {
+                return Err(raw_nodes.invalid("broken tree"));
+            }
+        }
+        Ok(UndoHistory { nodes, current })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct UndoNode {
+    pub parent: Option<UndoNodeId>,
+    pub last_child: Option<UndoNodeId>,
+    pub timestamp: SystemTime,
+    pub operation: UndoOperation,
+}
+
+impl nojson::DisplayJson for UndoNode {
+    fn fmt(&self, f: &mut nojson::JsonFormatter<'_, '_>) -> std::fmt::Result {
+        let timestamp = self
+            .timestamp
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        f.object(|f| {
+            f.member("parent", self.parent)?;
+            f.member("last_child", self.last_child)?;
+            f.member("timestamp", timestamp)?;
+            f.member("operation", &self.operation)
+        })
+    }
+}
+
+impl<'text, 'raw> TryFrom<nojson::RawJsonValue<'text, 'raw>> for UndoNode {
+    type Error = nojson::JsonParseError;
+
+    fn try_from(value: nojson::RawJsonValue<'text, 'raw>) -> Result<Self, Self::Error> {
+        let parent = value.to_member("parent")?.required()?;
+        let last_child = value.to_member("last_child")?.required()?;
+        let timestamp = value.to_member("timestamp")?.required()?;
+        let operation = value.to_member("operation")?.required()?;
+        Ok(UndoNode {
+            parent: parent.try_into()?,
+            last_child: last_child.try_into()?,
+            timestamp: UNIX_EPOCH + Duration::from_secs(timestamp.try_into()?),
+            operation: operation.try_into()?,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum UndoOperation {
+    // (position, old char, new char)
+    BulkUpdate {
+        updates: Vec<(TextPosition, char, char)>,
+    },
+    ReplaceLines {
+        row: usize,
+        old_lines: Vec<String>,
+        new_lines: Vec<String>,
+    },
+}
+
+impl UndoOperation {
+    pub fn positions(&self) -> impl '_ + Iterator<Item = TextPosition> {
+        match self {
+            UndoOperation::BulkUpdate { updates } => {
+                Box::new(updates.iter().map(|(pos, _, _)| *pos)) as Box<dyn Iterator<Item = _>>
+            }
+            UndoOperation::ReplaceLines {
+                row,
+                old_lines,
+                new_lines,
+            } => {
+                let rows = old_lines.len().max(new_lines.len());
+                Box::new((*row..*row + rows).map(|row| TextPosition { row, col: 0 }))
+            }
+        }
+    }
+}
+
+impl nojson::DisplayJson for UndoOperation {
+    fn fmt(&self, f: &mut nojson::JsonFormatter<'_, '_>) -> std::fmt::Result {
+        match self {
+            UndoOperation::BulkUpdate { updates } => f.object(|f| {
+                f.member(
+                    "bulk_update",
+                    nojson::json(|f| {
+                        f.array(|f| {
+                            for (pos, old, new) in updates {
+                                f.element(nojson::json(|f| {
+                                    f.array(|f| {
+                                        f.element(pos.row)?;
+                                        f.element(pos.col)?;
+                                        f.element(old.to_string())?;
+                                        f.element(new.to_string())
+                                    })
+                                }))?;
+                            }
+                            Ok(())
+                        })
+                    }),
+                )
+            }),
+            UndoOperation::ReplaceLines {
+                row,
+                old_lines,
+                new_lines,
+            } => f.object(|f| {
+                f.member(
+                    "replace_lines",
+                    nojson::json(|f| {
+                        f.object(|f| {
+                            f.member("row", row)?;
+                            f.member("old_lines", old_lines)?;
+                            f.member("new_lines", new_lines)
+                        })
+                    }),
+                )
+            }),
+        }
+    }
+}
+
+impl<'text, 'raw> TryFrom<nojson::RawJsonValue<'text, 'raw>> for UndoOperation {
+    type Error = nojson::JsonParseError;
+
+    fn try_from(value: nojson::RawJsonValue<'text, 'raw>) -> Result<Self, Self::Error> {
+        if let Some(raw) = value.to_member("replace_lines")?.get() {
+            let row = raw.to_member("row")?.required()?;
+            let old_lines = raw.to_member("old_lines")?.required()?;
+            let new_lines = raw.to_member("new_lines")?.required()?;
+            return Ok(UndoOperation::ReplaceLines {
+                row: row.try_into()?,
+                old_lines: old_lines.try_into()?,
+                new_lines: new_lines.try_into()?,
+            });
+        }
+
+        let raw_updates = value.to_member("bulk_update")?.required()?;
+        let mut updates = Vec::new();
+        for raw_update in raw_updates.to_array()? {
+            let (row, col, old, new): (usize, usize, String, String) = raw_update.try_into()?;
+            let (Some(old), Some(new)) = (single_char(&old), single_char(&new)) else {
+                return Err(raw_update.invalid("expected a single character"));
+            };
+            updates.push((TextPosition { row, col }, old, new));
+        }
+        Ok(UndoOperation::BulkUpdate { updates })
+    }
+}
+
+pub(crate) fn single_char(s: &str) -> Option<char> {
+    let mut chars = s.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Some(c),
+        _ => None,
+    }
+}
@@ -33,11 +33,14 @@ impl std::fmt::Display for KeySequence {
 pub struct KeyBindings {
     pub main: KeyBindingsGroup,
     pub clipboard: KeyBindingsGroup,
+    pub undo_tree: KeyBindingsGroup,
+    pub stamp_picker: KeyBindingsGroup,
     pub global: Option<KeyBindingsGroup>,
     pub groups: BTreeMap<String, KeyBindingsGroup>,
 }
 
 impl KeyBindings {
+    #[expect(clippy::result_unit_err)]
     pub fn find<'a>(
         &'a self,
         group: &'a KeyBindingsGroup,
@@ -78,14 +81,14 @@ impl KeyBindings {
                     // No prefix, so this key is a possible first key
                     // Always insert the command (whether it's complete or Scope)
                     results.insert(entry.keys.clone(), entry.command.clone()); // TODO} remove clone
-                } else if prefix.0.len() == 1 && prefix.0[0] == key {
+                } else if prefix.0.len() == 1
+                    && prefix.0[0] == key
                     // This key matches our prefix, check what comes next
-                    if let EditorCommand::Scope(scope_name) = &entry.command {
-                        if let Some(scoped_group) = self.groups.get(scope_name) {
-                            let empty_prefix = KeySequence(vec![]);
-                            self.collect_possible_commands(scoped_group, &empty_prefix, results);
-                        }
-                    }
+                    && let EditorCommand::Scope(scope_name) = &entry.command
+                    && let Some(scoped_group) = self.groups.get(scope_name)
+                {
+                    let empty_prefix = KeySequence(vec![]);
+                    self.collect_possible_commands(scoped_group, &empty_prefix, results);
                 }
             }
         }
@@ -164,7 +167,12 @@ impl<'text, 'raw> TryFrom<nojson::RawJsonValue<'text, 'raw>> for KeyBindings {
         group_names.retain(|n| {
             !matches!(
                 n.as_str(),
-                "__main__" | "__global__" | "__clipboard__" | "__comment__"
+                "__main__"
+                    | "__global__"
+                    | "__clipboard__"
+                    | "__undo_tree__"
+                    | "__stamp_picker__"
+                    | "__comment__"
             )
         });
 
@@ -177,7 +185,14 @@ impl<'text, 'raw> TryFrom<nojson::RawJsonValue<'text, 'raw>> for KeyBindings {
 
             if name.starts_with("__")
                 && name.ends_with("__")
-                && !matches!(name.as_ref(), "__main__" | "__global__" | "__clipboard__")
+                && !matches!(
+                    name.as_ref(),
+                    "__main__"
+                        | "__global__"
+                        | "__clipboard__"
+                        | "__undo_tree__"
+                        | "__stamp_picker__"
+                )
             {
                 return Err(raw_name.invalid("no such built-in group"));
             }
@@ -192,16 +207,31 @@ impl<'text, 'raw> TryFrom<nojson::RawJsonValue<'text, 'raw>> for KeyBindings {
         let clipboard = groups
             .remove("__clipboard__")
             .ok_or_else(|| value.invalid("missing __clipboard__ group"))?;
+        // Built-in groups added later fall back to the defaults so that older configs keep working
+        let undo_tree = groups
+            .remove("__undo_tree__")
+            .unwrap_or_else(|| Self::default().undo_tree);
+        let stamp_picker = groups
+            .remove("__stamp_picker__")
+            .unwrap_or_else(|| Self::default().stamp_picker);
         let global = groups.remove("__global__");
         Ok(KeyBindings {
             main,
             clipboard,
+            undo_tree,
+            stamp_picker,
             global,
             groups,
         })
     }
 }
 
+impl Default for KeyBindings {
+    fn default() -> Self {
+        crate::config::Config::default().keybindings
+    }
+}
+
 #[derive(Debug)]
 pub struct KeyBindingsGroup {
     pub entries: Vec<KeyBindingEntry>,
@@ -238,10 +268,10 @@ impl KeyBindingEntry {
             .to_unquoted_string_str()?
             .parse::<EditorCommand>()
             .map_err(|e| raw_command.invalid(e))?;
-        if let EditorCommand::Scope(group_name) = &command {
-            if !group_names.contains(group_name) {
-                return Err(raw_command.invalid("no such group"));
-            }
+        if let EditorCommand::Scope(group_name) = &command
+            && !group_names.contains(group_name)
+        {
+            return Err(raw_command.invalid("no such group"));
         }
         Ok(Self { keys, command })
     }
@@ -275,7 +305,6 @@ impl std::fmt::Display for KeySet {
 
 #[cfg(test)]
 mod tests {
-    use nojson::FromRawJsonValue;
     use orfail::OrFail;
 
     use super::*;
@@ -284,11 +313,23 @@ mod tests {
     fn parse_key_bindings() -> orfail::Result<()> {
         let json = include_str!("../default.config.json");
         let json = nojson::RawJson::parse(json).or_fail()?;
-        let ([keybindings], []) = json
+        let keybindings = json
             .value()
-            .to_fixed_object(["keybindings"], [])
+            .to_member("keybindings")
+            .or_fail()?
+            .required()
             .or_fail()?;
-        KeyBindings::from_raw_json_value(keybindings).or_fail()?;
+        KeyBindings::try_from(keybindings).or_fail()?;
+        Ok(())
+    }
+
+    #[test]
+    fn missing_builtin_groups() -> orfail::Result<()> {
+        let json = r#"{"__main__": {"q": "quit"}, "__clipboard__": {"v": "paste"}}"#;
+        let json = nojson::RawJson::parse(json).or_fail()?;
+        let keybindings = KeyBindings::try_from(json.value()).or_fail()?;
+        assert!(!keybindings.undo_tree.entries.is_empty());
+        assert!(!keybindings.stamp_picker.entries.is_empty());
         Ok(())
     }
 }
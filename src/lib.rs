@@ -1,14 +1,29 @@
 pub mod app;
+pub mod brush;
 pub mod buffer;
+pub mod canvas;
 pub mod clipboard;
 pub mod config;
 pub mod editor;
 pub mod editor_command;
+pub mod history;
 pub mod keybinding;
 pub mod marker;
+pub mod registers;
+pub mod selection;
+pub mod stamp;
+pub mod symmetry;
+pub mod system_clipboard;
+pub mod terminal_input;
+#[cfg(test)]
+mod test_util;
 pub mod tuinix_ext;
+pub mod undo_journal;
 pub mod widget_legend;
 pub mod widget_message;
 pub mod widget_preview;
+pub mod widget_stamp_picker;
 pub mod widget_status;
 pub mod widget_text;
+pub mod widget_thumbnail_list;
+pub mod widget_undo_tree;
@@ -5,6 +5,7 @@ use orfail::OrFail;
 use utu::{
     app::App,
     buffer::TextPosition,
+    canvas::{self, FrameStyle},
     config::{Config, FrameSize},
 };
 
@@ -31,6 +32,15 @@ fn main() -> noargs::Result<()> {
             Ok(config)
         })?
         .unwrap_or_default();
+
+    if noargs::cmd("new")
+        .doc("Create a blank canvas file and edit it")
+        .take(&mut args)
+        .is_present()
+    {
+        return run_new(args, config);
+    }
+
     let position: TextPosition = noargs::opt("position")
         .short('p')
         .ty("ROW:COLUMN")
@@ -90,6 +100,54 @@ fn main() -> noargs::Result<()> {
     Ok(())
 }
 
+fn run_new(mut args: noargs::RawArgs, config: Config) -> noargs::Result<()> {
+    let frame_style: FrameStyle = noargs::opt("frame")
+        .ty("ascii|none")
+        .default("ascii")
+        .doc("Frame style of the canvas")
+        .take(&mut args)
+        .then(|a| a.value().parse())?;
+    let markdown = noargs::flag("markdown")
+        .doc("Wrap the canvas in a Markdown code block")
+        .take(&mut args)
+        .is_present();
+    let size: FrameSize = noargs::arg("WIDTHxHEIGHT")
+        .doc("Canvas size")
+        .example("32x16")
+        .take(&mut args)
+        .then(|a| a.value().parse())?;
+    let file_path: PathBuf = noargs::arg("FILE_PATH")
+        .doc("File path to create")
+        .example("/path/to/file")
+        .take(&mut args)
+        .then(|a| {
+            let path = PathBuf::from(a.value());
+            if matches!(a, noargs::Arg::Example { .. }) || !path.exists() {
+                Ok(path)
+            } else {
+                Err("file already exists")
+            }
+        })?;
+    if let Some(help) = args.finish()? {
+        print!("{help}");
+        return Ok(());
+    }
+
+    let canvas_char = config.keybindings.canvas_char();
+    let lines = canvas::blank_canvas_lines(size, frame_style, canvas_char, markdown);
+    let mut content = lines.join("\n");
+    content.push('\n');
+    std::fs::write(&file_path, content).or_fail()?;
+
+    let mut app = App::new(file_path, config).or_fail()?;
+    app.editor.cursor = TextPosition {
+        row: usize::from(markdown) + usize::from(frame_style == FrameStyle::Ascii),
+        col: usize::from(frame_style == FrameStyle::Ascii),
+    };
+    app.run().or_fail()?;
+    Ok(())
+}
+
 fn generate_png_from_buffer(
     buffer: &utu::buffer::TextBuffer,
     config: &utu::config::Config,
@@ -1,6 +1,6 @@
 use std::collections::BTreeSet;
 
-use crate::{buffer::TextPosition, editor::Editor};
+use crate::{buffer::TextPosition, canvas::Canvas, editor::Editor};
 
 #[derive(Debug, Clone)]
 pub enum Marker {
@@ -9,11 +9,14 @@ pub enum Marker {
     Rect(RectMarker),
     FilledRect(FilledRectMarker),
     Fill(FillMarker),
+    Ellipse(EllipseMarker),
+    Polygon(PolygonMarker),
+    Curve(CurveMarker),
 }
 
 impl Marker {
-    pub fn new_stroke(editor: &Editor) -> Self {
-        Self::Stroke(StrokeMarker::new(editor))
+    pub fn new_stroke(editor: &Editor, pixel_perfect: bool) -> Self {
+        Self::Stroke(StrokeMarker::new(editor, pixel_perfect))
     }
 
     pub fn new_line(editor: &Editor) -> Self {
@@ -24,31 +27,66 @@ impl Marker {
         Self::Rect(RectMarker::new(editor))
     }
 
-    pub fn new_fill(editor: &Editor) -> Self {
-        Self::Fill(FillMarker::new(editor))
+    pub fn new_fill(editor: &Editor, mode: FillMode, tolerance: u32) -> Self {
+        Self::Fill(FillMarker::new(editor, mode, tolerance))
     }
 
     pub fn new_filled_rect(editor: &Editor) -> Self {
         Self::FilledRect(FilledRectMarker::new(editor))
     }
 
+    pub fn new_ellipse(editor: &Editor, filled: bool, circle: bool) -> Self {
+        Self::Ellipse(EllipseMarker::new(editor, filled, circle))
+    }
+
+    pub fn new_polygon(editor: &Editor, filled: bool) -> Self {
+        Self::Polygon(PolygonMarker::new(editor, filled))
+    }
+
+    pub fn new_curve(editor: &Editor) -> Self {
+        Self::Curve(CurveMarker::new(editor))
+    }
+
     pub fn name(&self) -> &'static str {
         match self {
             Marker::Stroke(_) => "MARK(STROKE)",
             Marker::Line(_) => "MARK(LINE)",
             Marker::Rect(_) => "MARK(RECT)",
             Marker::FilledRect(_) => "MARK(FILLED_RECT)",
-            Marker::Fill(_) => "MARK(FILL)",
+            Marker::Fill(m) => match m.mode {
+                FillMode::Connected4 => "MARK(FILL)",
+                FillMode::Connected8 => "MARK(FILL8)",
+                FillMode::Global => "MARK(REPLACE)",
+            },
+            Marker::Ellipse(m) if m.filled => "MARK(FILLED_ELLIPSE)",
+            Marker::Ellipse(_) => "MARK(ELLIPSE)",
+            Marker::Polygon(m) if m.filled => "MARK(FILLED_POLYGON)",
+            Marker::Polygon(m) if m.closed => "MARK(POLYGON)",
+            Marker::Polygon(_) => "MARK(POLYLINE)",
+            Marker::Curve(_) => "MARK(CURVE)",
         }
     }
 
+    // Positions are in pixel units (see `TextBuffer::to_pixel_position()`)
     pub fn marked_positions(&self) -> Box<dyn '_ + Iterator<Item = TextPosition>> {
         match self {
-            Marker::Stroke(m) => Box::new(m.positions.iter().copied()),
+            Marker::Stroke(m) => Box::new(m.marked_positions()),
             Marker::Line(m) => Box::new(m.marked_positions()),
             Marker::Rect(m) => Box::new(m.marked_positions()),
             Marker::FilledRect(m) => Box::new(m.marked_positions()),
             Marker::Fill(m) => Box::new(m.filled_positions.iter().copied()),
+            Marker::Ellipse(m) => Box::new(m.marked_positions().into_iter()),
+            Marker::Polygon(m) => Box::new(m.marked_positions().into_iter()),
+            Marker::Curve(m) => Box::new(m.marked_positions().into_iter()),
+        }
+    }
+
+    // Whether the marked positions are painted with the brush
+    pub fn uses_brush(&self) -> bool {
+        match self {
+            Marker::Stroke(_) | Marker::Line(_) | Marker::Curve(_) => true,
+            Marker::Polygon(m) => !m.filled,
+            _ => false,
         }
     }
 
@@ -59,24 +97,46 @@ impl Marker {
             Marker::Rect(m) => m.handle_cursor_move(editor),
             Marker::FilledRect(m) => m.handle_cursor_move(editor),
             Marker::Fill(m) => m.handle_cursor_move(editor),
+            Marker::Ellipse(m) => m.handle_cursor_move(editor),
+            Marker::Polygon(m) => m.handle_cursor_move(editor),
+            Marker::Curve(m) => m.handle_cursor_move(editor),
         }
     }
 }
 
 #[derive(Debug, Clone)]
 pub struct StrokeMarker {
-    positions: BTreeSet<TextPosition>,
+    // Visited positions in order
+    path: Vec<TextPosition>,
+    pixel_perfect: bool,
 }
 
 impl StrokeMarker {
-    fn new(editor: &Editor) -> Self {
+    fn new(editor: &Editor, pixel_perfect: bool) -> Self {
         Self {
-            positions: [editor.cursor].into_iter().collect(),
+            path: vec![editor.pixel_cursor()],
+            pixel_perfect,
         }
     }
 
     fn handle_cursor_move(&mut self, editor: &Editor) {
-        self.positions.insert(editor.cursor);
+        self.push(editor.pixel_cursor());
+    }
+
+    fn push(&mut self, pos: TextPosition) {
+        if self.pixel_perfect {
+            push_pixel_perfect(&mut self.path, pos);
+        } else if self.path.last() != Some(&pos) {
+            self.path.push(pos);
+        }
+    }
+
+    fn marked_positions(&self) -> impl Iterator<Item = TextPosition> {
+        self.path
+            .iter()
+            .copied()
+            .collect::<BTreeSet<_>>()
+            .into_iter()
     }
 }
 
@@ -89,42 +149,175 @@ pub struct LineMarker {
 impl LineMarker {
     fn new(editor: &Editor) -> Self {
         Self {
-            start: editor.cursor,
-            end: editor.cursor,
+            start: editor.pixel_cursor(),
+            end: editor.pixel_cursor(),
         }
     }
 
     fn handle_cursor_move(&mut self, editor: &Editor) {
-        self.end = editor.cursor;
+        self.end = editor.pixel_cursor();
     }
 
     fn marked_positions(&self) -> impl Iterator<Item = TextPosition> + '_ {
-        let start = self.start;
-        let end = self.end;
+        line_positions(self.start, self.end)
+    }
+}
 
-        // Calculate the line using simple interpolation
-        let dx = end.col as i32 - start.col as i32;
-        let dy = end.row as i32 - start.row as i32;
+// Appends `pos` to `path`, dropping the corner pixel of an "L" shape so that diagonals are one pixel thick
+fn push_pixel_perfect(path: &mut Vec<TextPosition>, pos: TextPosition) {
+    if path.last() == Some(&pos) {
+        return;
+    }
+    if let [.., a, b] = path[..]
+        && a.row.abs_diff(pos.row) == 1
+        && a.col.abs_diff(pos.col) == 1
+        && (b.row == a.row || b.col == a.col)
+        && (b.row == pos.row || b.col == pos.col)
+    {
+        path.pop();
+    }
+    path.push(pos);
+}
 
-        let steps = std::cmp::max(dx.abs(), dy.abs()) as usize;
+// Bresenham's line algorithm (integer only, one pixel per step along the major axis)
+fn line_positions(start: TextPosition, end: TextPosition) -> impl Iterator<Item = TextPosition> {
+    let (mut x, mut y) = (start.col as i64, start.row as i64);
+    let (x1, y1) = (end.col as i64, end.row as i64);
+    let dx = (x1 - x).abs();
+    let dy = -(y1 - y).abs();
+    let sx = if x < x1 { 1 } else { -1 };
+    let sy = if y < y1 { 1 } else { -1 };
+    let mut err = dx + dy;
+    let mut done = false;
+
+    std::iter::from_fn(move || {
+        if done {
+            return None;
+        }
+        let pos = TextPosition {
+            row: y as usize,
+            col: x as usize,
+        };
+        if x == x1 && y == y1 {
+            done = true;
+            return Some(pos);
+        }
+        let e2 = 2 * err;
+        if e2 >= dy {
+            err += dy;
+            x += sx;
+        }
+        if e2 <= dx {
+            err += dx;
+            y += sy;
+        }
+        Some(pos)
+    })
+}
 
-        (0..=steps).map(move |i| {
-            if steps == 0 {
-                return start;
-            }
+// Connected line segments. The segment being drawn ends at the cursor until its vertex is committed.
+#[derive(Debug, Clone)]
+pub struct PolygonMarker {
+    vertices: Vec<TextPosition>,
+    end: TextPosition,
+    closed: bool,
+    filled: bool,
+}
 
-            let t = i as f64 / steps as f64;
-            let col = start.col as f64 + t * dx as f64;
-            let row = start.row as f64 + t * dy as f64;
+impl PolygonMarker {
+    fn new(editor: &Editor, filled: bool) -> Self {
+        Self {
+            vertices: vec![editor.pixel_cursor()],
+            end: editor.pixel_cursor(),
+            closed: false,
+            filled,
+        }
+    }
 
-            TextPosition {
-                row: row.round() as usize,
-                col: col.round() as usize,
-            }
-        })
+    fn handle_cursor_move(&mut self, editor: &Editor) {
+        if !self.closed {
+            self.end = editor.pixel_cursor();
+        }
+    }
+
+    // Returns `false` if the polygon is already closed
+    pub fn commit_vertex(&mut self) -> bool {
+        if self.closed {
+            return false;
+        }
+        if self.vertices.last() != Some(&self.end) {
+            self.vertices.push(self.end);
+        }
+        true
+    }
+
+    // Returns `false` if the polygon is already closed
+    pub fn close(&mut self) -> bool {
+        if !self.commit_vertex() {
+            return false;
+        }
+        self.closed = true;
+        true
+    }
+
+    fn marked_positions(&self) -> Vec<TextPosition> {
+        let mut vertices = self.vertices.clone();
+        if vertices.last() != Some(&self.end) {
+            vertices.push(self.end);
+        }
+
+        let mut edges: Vec<_> = vertices.windows(2).map(|w| (w[0], w[1])).collect();
+        if (self.closed || self.filled) && vertices.len() > 2 {
+            edges.push((vertices[vertices.len() - 1], vertices[0]));
+        }
+
+        let mut positions: BTreeSet<_> = edges
+            .iter()
+            .flat_map(|&(start, end)| line_positions(start, end))
+            .collect();
+        positions.insert(vertices[0]);
+        if self.filled {
+            positions.extend(scanline_fill(&edges));
+        }
+        positions.into_iter().collect()
     }
 }
 
+// Interior pixels of the polygon enclosed by `edges` (even-odd rule)
+fn scanline_fill(edges: &[(TextPosition, TextPosition)]) -> Vec<TextPosition> {
+    let Some(min_row) = edges.iter().map(|(a, b)| a.row.min(b.row)).min() else {
+        return Vec::new();
+    };
+    let max_row = edges
+        .iter()
+        .map(|(a, b)| a.row.max(b.row))
+        .max()
+        .unwrap_or(min_row);
+
+    let mut positions = Vec::new();
+    for row in min_row..=max_row {
+        // Sample at pixel centers; edges are half-open in rows so shared vertices count once
+        let y = row as f64;
+        let mut xs: Vec<f64> = edges
+            .iter()
+            .filter(|(a, b)| a.row != b.row)
+            .filter(|(a, b)| (a.row.min(b.row)..a.row.max(b.row)).contains(&row))
+            .map(|(a, b)| {
+                let (x0, y0) = (a.col as f64, a.row as f64);
+                let (x1, y1) = (b.col as f64, b.row as f64);
+                x0 + (y - y0) * (x1 - x0) / (y1 - y0)
+            })
+            .collect();
+        xs.sort_by(f64::total_cmp);
+        for pair in xs.chunks_exact(2) {
+            let left = pair[0].ceil() as usize;
+            let right = pair[1].floor() as usize;
+            positions.extend((left..=right).map(|col| TextPosition { row, col }));
+        }
+    }
+    positions
+}
+
 #[derive(Debug, Clone)]
 pub struct RectMarker {
     start: TextPosition,
@@ -134,13 +327,13 @@ pub struct RectMarker {
 impl RectMarker {
     fn new(editor: &Editor) -> Self {
         Self {
-            start: editor.cursor,
-            end: editor.cursor,
+            start: editor.pixel_cursor(),
+            end: editor.pixel_cursor(),
         }
     }
 
     fn handle_cursor_move(&mut self, editor: &Editor) {
-        self.end = editor.cursor;
+        self.end = editor.pixel_cursor();
     }
 
     fn marked_positions(&self) -> impl Iterator<Item = TextPosition> + '_ {
@@ -176,13 +369,13 @@ pub struct FilledRectMarker {
 impl FilledRectMarker {
     fn new(editor: &Editor) -> Self {
         Self {
-            start: editor.cursor,
-            end: editor.cursor,
+            start: editor.pixel_cursor(),
+            end: editor.pixel_cursor(),
         }
     }
 
     fn handle_cursor_move(&mut self, editor: &Editor) {
-        self.end = editor.cursor;
+        self.end = editor.pixel_cursor();
     }
 
     fn marked_positions(&self) -> impl Iterator<Item = TextPosition> + '_ {
@@ -201,17 +394,271 @@ impl FilledRectMarker {
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct EllipseMarker {
+    start: TextPosition,
+    end: TextPosition,
+    filled: bool,
+    circle: bool,
+}
+
+impl EllipseMarker {
+    fn new(editor: &Editor, filled: bool, circle: bool) -> Self {
+        Self {
+            start: editor.pixel_cursor(),
+            end: editor.pixel_cursor(),
+            filled,
+            circle,
+        }
+    }
+
+    fn handle_cursor_move(&mut self, editor: &Editor) {
+        self.end = editor.pixel_cursor();
+    }
+
+    fn marked_positions(&self) -> Vec<TextPosition> {
+        let start = self.start;
+        let mut end = self.end;
+        if self.circle {
+            // Extend the shorter side of the bounding box to make it a square
+            // (shrunk so that it does not go beyond row 0 or column 0)
+            let mut size = start.row.abs_diff(end.row).max(start.col.abs_diff(end.col));
+            if end.row < start.row {
+                size = size.min(start.row);
+            }
+            if end.col < start.col {
+                size = size.min(start.col);
+            }
+            let extend = |from: usize, to: usize| {
+                if to < from { from - size } else { from + size }
+            };
+            end = TextPosition {
+                row: extend(start.row, end.row),
+                col: extend(start.col, end.col),
+            };
+        }
+
+        let min = TextPosition {
+            row: start.row.min(end.row),
+            col: start.col.min(end.col),
+        };
+        let max = TextPosition {
+            row: start.row.max(end.row),
+            col: start.col.max(end.col),
+        };
+        ellipse_positions(min, max, self.filled)
+    }
+}
+
+// Pixels of the ellipse inscribed in the rectangle from `min` to `max` (inclusive).
+// Based on the midpoint algorithm by Alois Zingl ("A Rasterizing Algorithm for Drawing Curves").
+pub(crate) fn ellipse_positions(
+    min: TextPosition,
+    max: TextPosition,
+    filled: bool,
+) -> Vec<TextPosition> {
+    let (mut x0, mut y0) = (min.col as i64, min.row as i64);
+    let (mut x1, mut y1) = (max.col as i64, max.row as i64);
+    let a = x1 - x0;
+    let b = y1 - y0;
+    let b1 = b & 1;
+    let mut dx = 4 * (1 - a) * b * b;
+    let mut dy = 4 * (b1 + 1) * a * a;
+    let mut err = dx + dy + b1 * a * a;
+    y0 += (b + 1) / 2;
+    y1 = y0 - b1;
+    let aa8 = 8 * a * a;
+    let bb8 = 8 * b * b;
+
+    let mut points = Vec::new();
+    loop {
+        points.extend([(x1, y0), (x0, y0), (x0, y1), (x1, y1)]);
+        let e2 = 2 * err;
+        if e2 <= dy {
+            y0 += 1;
+            y1 -= 1;
+            dy += aa8;
+            err += dy;
+        }
+        if e2 >= dx || 2 * err > dy {
+            x0 += 1;
+            x1 -= 1;
+            dx += bb8;
+            err += dx;
+        }
+        if x0 > x1 {
+            break;
+        }
+    }
+
+    // Finish the tips of flat ellipses
+    while y0 - y1 < b {
+        points.extend([(x0 - 1, y0), (x1 + 1, y0), (x0 - 1, y1), (x1 + 1, y1)]);
+        y0 += 1;
+        y1 -= 1;
+    }
+
+    // The outline never goes beyond the rectangle, so the points are non-negative
+    let outline: BTreeSet<_> = points
+        .into_iter()
+        .map(|(x, y)| TextPosition {
+            row: y as usize,
+            col: x as usize,
+        })
+        .collect();
+    if !filled {
+        return outline.into_iter().collect();
+    }
+
+    // Fill the span between the leftmost and rightmost outline pixels in each row
+    let mut spans = std::collections::BTreeMap::<usize, (usize, usize)>::new();
+    for pos in outline {
+        let span = spans.entry(pos.row).or_insert((pos.col, pos.col));
+        span.0 = span.0.min(pos.col);
+        span.1 = span.1.max(pos.col);
+    }
+    spans
+        .into_iter()
+        .flat_map(|(row, (left, right))| (left..=right).map(move |col| TextPosition { row, col }))
+        .collect()
+}
+
+// Bézier curve defined by start, end and up to two control points, committed in this order
+#[derive(Debug, Clone)]
+pub struct CurveMarker {
+    points: Vec<TextPosition>,
+    current: TextPosition,
+}
+
+impl CurveMarker {
+    const MAX_POINTS: usize = 4;
+
+    fn new(editor: &Editor) -> Self {
+        Self {
+            points: vec![editor.pixel_cursor()],
+            current: editor.pixel_cursor(),
+        }
+    }
+
+    fn handle_cursor_move(&mut self, editor: &Editor) {
+        self.current = editor.pixel_cursor();
+    }
+
+    // Commits the current point and returns `false` if all points have already been committed
+    pub fn commit_point(&mut self) -> bool {
+        if self.points.len() >= Self::MAX_POINTS {
+            return false;
+        }
+        self.points.push(self.current);
+        true
+    }
+
+    fn marked_positions(&self) -> Vec<TextPosition> {
+        let mut points = self.points.clone();
+        if points.len() < Self::MAX_POINTS {
+            points.push(self.current);
+        }
+
+        // Reorder as [start, control points.., end]
+        let end = points.remove(1);
+        let points: Vec<(f64, f64)> = points
+            .into_iter()
+            .chain(std::iter::once(end))
+            .map(|p| (p.col as f64, p.row as f64))
+            .collect();
+
+        // Sample densely enough, then connect and thin the samples
+        let length: f64 = points
+            .windows(2)
+            .map(|w| (w[1].0 - w[0].0).abs() + (w[1].1 - w[0].1).abs())
+            .sum();
+        let steps = (length * 2.0).ceil().max(1.0) as usize;
+        let mut path = Vec::new();
+        let mut prev: Option<TextPosition> = None;
+        for i in 0..=steps {
+            let (x, y) = bezier_point(&points, i as f64 / steps as f64);
+            let pos = TextPosition {
+                row: y.round() as usize,
+                col: x.round() as usize,
+            };
+            for p in line_positions(prev.unwrap_or(pos), pos) {
+                push_pixel_perfect(&mut path, p);
+            }
+            prev = Some(pos);
+        }
+        path
+    }
+}
+
+// De Casteljau's algorithm
+fn bezier_point(points: &[(f64, f64)], t: f64) -> (f64, f64) {
+    let mut points = points.to_vec();
+    while points.len() > 1 {
+        points = points
+            .windows(2)
+            .map(|w| {
+                (
+                    w[0].0 + (w[1].0 - w[0].0) * t,
+                    w[0].1 + (w[1].1 - w[0].1) * t,
+                )
+            })
+            .collect();
+    }
+    points[0]
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum FillMode {
+    // Flood fill through up, down, left and right neighbors
+    #[default]
+    Connected4,
+    // Flood fill through diagonal neighbors as well
+    Connected8,
+    // Every position of the same char regardless of connectivity
+    Global,
+}
+
+impl std::fmt::Display for FillMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FillMode::Connected4 => write!(f, "4"),
+            FillMode::Connected8 => write!(f, "8"),
+            FillMode::Global => write!(f, "global"),
+        }
+    }
+}
+
+impl std::str::FromStr for FillMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "4" => Ok(FillMode::Connected4),
+            "8" => Ok(FillMode::Connected8),
+            "global" => Ok(FillMode::Global),
+            _ => Err(format!("unknown fill mode: {s}")),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct FillMarker {
+    mode: FillMode,
+    // Maximum palette colour distance to be treated as the same char
+    tolerance: u32,
     position: TextPosition,
+    canvas: Option<Canvas>,
     target_char: Option<char>,
     filled_positions: BTreeSet<TextPosition>,
 }
 
 impl FillMarker {
-    fn new(editor: &Editor) -> Self {
+    fn new(editor: &Editor, mode: FillMode, tolerance: u32) -> Self {
         let mut marker = Self {
-            position: editor.cursor,
+            mode,
+            tolerance,
+            position: editor.pixel_cursor(),
+            canvas: editor.current_canvas(),
             target_char: None, // Initialize as None, will be set on first update
             filled_positions: BTreeSet::new(),
         };
@@ -220,7 +667,7 @@ impl FillMarker {
     }
 
     fn handle_cursor_move(&mut self, editor: &Editor) {
-        self.position = editor.cursor;
+        self.position = editor.pixel_cursor();
         self.update_filled_positions(editor);
     }
 
@@ -230,7 +677,7 @@ impl FillMarker {
         let start_pos = self.position;
 
         // Get the character at the current position (None for background/empty positions)
-        let target_char = editor.buffer.get_char_at(start_pos);
+        let target_char = editor.char_at_pixel(start_pos);
 
         // Only update if the character has changed
         if self.target_char != target_char {
@@ -242,8 +689,45 @@ impl FillMarker {
             return;
         }
 
-        // Perform flood fill
-        self.flood_fill(editor, start_pos, self.target_char);
+        if self.mode == FillMode::Global {
+            self.replace_all(editor, self.target_char);
+        } else {
+            self.flood_fill(editor, start_pos, self.target_char);
+        }
+    }
+
+    fn matches(&self, editor: &Editor, c: Option<char>, target_char: Option<char>) -> bool {
+        if c == target_char {
+            return true;
+        }
+        let colors = &editor.config.palette.colors;
+        let (Some(c), Some(target_char)) = (c, target_char) else {
+            return false;
+        };
+        let (Some(color), Some(target_color)) = (colors.get(&c), colors.get(&target_char)) else {
+            return false;
+        };
+        color.distance(*target_color) <= self.tolerance
+    }
+
+    fn replace_all(&mut self, editor: &Editor, target_char: Option<char>) {
+        let positions = match self.canvas {
+            Some(canvas) => canvas.positions(&editor.buffer),
+            None => (0..editor.buffer.rows())
+                .flat_map(|row| {
+                    editor
+                        .buffer
+                        .char_cols(row)
+                        .map(move |(col, _)| TextPosition { row, col })
+                })
+                .collect(),
+        };
+        self.filled_positions = positions
+            .into_iter()
+            .filter(|pos| !editor.canvases().iter().any(|c| c.is_on_frame(*pos)))
+            .filter(|pos| self.matches(editor, editor.buffer.get_char_at(*pos), target_char))
+            .map(|pos| editor.buffer.to_pixel_position(pos))
+            .collect();
     }
 
     fn flood_fill(&mut self, editor: &Editor, start_pos: TextPosition, target_char: Option<char>) {
@@ -251,10 +735,20 @@ impl FillMarker {
 
         while let Some(current_pos) = stack.pop() {
             // Get character at current position (None for background/empty positions)
-            let current_char = editor.buffer.get_char_at(current_pos);
+            let current_char = editor.char_at_pixel(current_pos);
 
             // If character doesn't match target or position already visited, skip
-            if current_char != target_char || self.filled_positions.contains(&current_pos) {
+            if !self.matches(editor, current_char, target_char)
+                || self.filled_positions.contains(&current_pos)
+            {
+                continue;
+            }
+
+            // Don't leak out of the canvas nor into frames
+            let text_pos = editor.buffer.to_text_position(current_pos);
+            if self.canvas.is_some_and(|canvas| !canvas.contains(text_pos))
+                || editor.canvases().iter().any(|c| c.is_on_frame(text_pos))
+            {
                 continue;
             }
 
@@ -278,14 +772,228 @@ impl FillMarker {
             if current_pos.col > 0 {
                 stack.push(TextPosition {
                     row: current_pos.row,
-                    col: current_pos.col - 1, // TODO: consider unicode width
+                    col: current_pos.col - 1,
                 });
             }
             // Right
             stack.push(TextPosition {
                 row: current_pos.row,
-                col: current_pos.col + 1, // TODO: consider unicode width
+                col: current_pos.col + 1,
             });
+
+            if self.mode == FillMode::Connected8 {
+                for (row, col) in [
+                    (
+                        current_pos.row.checked_sub(1),
+                        current_pos.col.checked_sub(1),
+                    ),
+                    (current_pos.row.checked_sub(1), Some(current_pos.col + 1)),
+                    (Some(current_pos.row + 1), current_pos.col.checked_sub(1)),
+                    (Some(current_pos.row + 1), Some(current_pos.col + 1)),
+                ] {
+                    if let (Some(row), Some(col)) = (row, col) {
+                        stack.push(TextPosition { row, col });
+                    }
+                }
+            }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::render;
+
+    #[test]
+    fn ellipse() {
+        let min = TextPosition { row: 0, col: 0 };
+        let max = TextPosition { row: 4, col: 6 };
+        assert_eq!(
+            render(&ellipse_positions(min, max, false), 5, 7),
+            ["..###..", ".#...#.", "#.....#", ".#...#.", "..###.."]
+        );
+        assert_eq!(
+            render(&ellipse_positions(min, max, true), 5, 7),
+            ["..###..", ".#####.", "#######", ".#####.", "..###.."]
+        );
+
+        // Even sizes and degenerate cases
+        let max = TextPosition { row: 3, col: 3 };
+        assert_eq!(
+            render(&ellipse_positions(min, max, false), 4, 4),
+            [".##.", "#..#", "#..#", ".##."]
+        );
+        let max = TextPosition { row: 0, col: 3 };
+        assert_eq!(render(&ellipse_positions(min, max, false), 1, 4), ["####"]);
+        assert_eq!(ellipse_positions(min, min, false), [min]);
+
+        // Circles near the top-left edges stay round
+        let circle = EllipseMarker {
+            start: TextPosition { row: 2, col: 5 },
+            end: TextPosition { row: 0, col: 0 },
+            filled: false,
+            circle: true,
+        };
+        assert_eq!(
+            render(&circle.marked_positions(), 3, 6),
+            ["....#.", "...#.#", "....#."]
+        );
+    }
+
+    #[test]
+    fn polygon() {
+        let pos = |row, col| TextPosition { row, col };
+        let mut marker = PolygonMarker {
+            vertices: vec![pos(0, 2)],
+            end: pos(4, 4),
+            closed: false,
+            filled: false,
+        };
+        assert!(marker.commit_vertex());
+        marker.end = pos(4, 0);
+        assert_eq!(
+            render(&marker.marked_positions(), 5, 5),
+            ["..#..", "...#.", "...#.", "....#", "#####"]
+        );
+
+        assert!(marker.close());
+        assert!(!marker.commit_vertex());
+        assert_eq!(
+            render(&marker.marked_positions(), 5, 5),
+            ["..#..", "..##.", ".#.#.", ".#..#", "#####"]
+        );
+
+        marker.filled = true;
+        assert_eq!(
+            render(&marker.marked_positions(), 5, 5),
+            ["..#..", "..##.", ".###.", ".####", "#####"]
+        );
+    }
+
+    #[test]
+    fn line() {
+        let start = TextPosition { row: 0, col: 0 };
+        let end = TextPosition { row: 2, col: 5 };
+        let positions: Vec<_> = line_positions(start, end).collect();
+        assert_eq!(render(&positions, 3, 6), ["##....", "..##..", "....##"]);
+        let reversed: Vec<_> = line_positions(end, start).collect();
+        assert_eq!(reversed.len(), positions.len());
+        assert_eq!(line_positions(start, start).collect::<Vec<_>>(), [start]);
+    }
+
+    #[test]
+    fn pixel_perfect_stroke() {
+        let pos = |row, col| TextPosition { row, col };
+        let path = [pos(0, 0), pos(0, 1), pos(1, 1), pos(1, 2), pos(2, 2)];
+        let mut stroke = StrokeMarker {
+            path: vec![pos(0, 0)],
+            pixel_perfect: true,
+        };
+        for p in &path[1..] {
+            stroke.push(*p);
+        }
+        assert_eq!(stroke.path, [pos(0, 0), pos(1, 1), pos(2, 2)]);
+
+        stroke.path = vec![pos(0, 0)];
+        stroke.pixel_perfect = false;
+        for p in &path[1..] {
+            stroke.push(*p);
+        }
+        assert_eq!(stroke.path, path);
+    }
+
+    #[test]
+    fn curve() {
+        let pos = |row, col| TextPosition { row, col };
+        let mut marker = CurveMarker {
+            points: vec![pos(4, 0)],
+            current: pos(4, 8),
+        };
+        assert_eq!(render(&marker.marked_positions(), 5, 9)[4], "#########");
+
+        assert!(marker.commit_point());
+        marker.current = pos(0, 4);
+        assert_eq!(
+            render(&marker.marked_positions(), 5, 9),
+            [
+                ".........",
+                ".........",
+                "..####...",
+                ".#....##.",
+                "#.......#"
+            ]
+        );
+
+        assert!(marker.commit_point());
+        assert!(marker.commit_point());
+        assert!(!marker.commit_point());
+    }
+
+    #[test]
+    fn fill_modes() -> orfail::Result<()> {
+        use orfail::OrFail;
+
+        let mut editor =
+            Editor::new("test.txt".into(), crate::config::Config::default()).or_fail()?;
+        editor.buffer.set_text("oo.o\n..o.\no...\n".to_owned());
+        let filled = |editor: &Editor, mode| {
+            let marker = FillMarker::new(editor, mode, 0);
+            render(
+                &marker.filled_positions.into_iter().collect::<Vec<_>>(),
+                3,
+                4,
+            )
+        };
+        assert_eq!(
+            filled(&editor, FillMode::Connected4),
+            ["##..", "....", "...."]
+        );
+        assert_eq!(
+            filled(&editor, FillMode::Connected8),
+            ["##.#", "..#.", "...."]
+        );
+        assert_eq!(filled(&editor, FillMode::Global), ["##.#", "..#.", "#..."]);
+        editor.cursor = TextPosition { row: 1, col: 0 };
+        assert_eq!(
+            filled(&editor, FillMode::Connected8),
+            ["..#.", "##.#", ".###"]
+        );
+        // Palette colour tolerance ('o' is close to '#' but '+' is not)
+        editor.buffer.set_text("#o+o\n....\n".to_owned());
+        editor.cursor = TextPosition { row: 0, col: 0 };
+        let filled = |mode| {
+            let marker = FillMarker::new(&editor, mode, 110);
+            render(
+                &marker.filled_positions.into_iter().collect::<Vec<_>>(),
+                2,
+                4,
+            )
+        };
+        assert_eq!(filled(FillMode::Connected4), ["##..", "...."]);
+        assert_eq!(filled(FillMode::Global), ["##.#", "...."]);
+        Ok(())
+    }
+
+    #[test]
+    fn wide_chars() -> orfail::Result<()> {
+        use orfail::OrFail;
+
+        let mut editor =
+            Editor::new("test.txt".into(), crate::config::Config::default()).or_fail()?;
+        editor.buffer.filter.fg_chars = ['⬜', '🟨'].into_iter().collect();
+        editor.buffer.set_text("🟨🟨⬜\n⬜🟨⬜\n".to_owned());
+        let pos = |row, col| TextPosition { row, col };
+
+        editor.marker = Some(Marker::new_fill(&editor, FillMode::Connected4, 0));
+        assert_eq!(editor.marked_positions(), [pos(0, 0), pos(0, 2), pos(1, 2)]);
+
+        editor.marker = Some(Marker::new_rect(&editor));
+        editor.move_cursor(pos(1, 2));
+        assert_eq!(
+            editor.marked_positions(),
+            [pos(0, 0), pos(0, 2), pos(1, 0), pos(1, 2)]
+        );
+        Ok(())
+    }
+}
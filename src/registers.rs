@@ -0,0 +1,83 @@
+use std::{collections::BTreeMap, path::PathBuf};
+
+use orfail::OrFail;
+
+use crate::clipboard::Clipboard;
+
+// Named clipboards shared across sessions (`$XDG_CONFIG_HOME/utu/registers.json`).
+// The file is re-read on every access so that multiple utu instances can share registers.
+#[derive(Debug)]
+pub struct Registers {
+    pub path: PathBuf,
+    pub clipboards: BTreeMap<String, Clipboard>,
+}
+
+impl Registers {
+    pub fn default_path() -> Option<PathBuf> {
+        let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+        Some(config_dir.join("utu").join("registers.json"))
+    }
+
+    pub fn load_default() -> orfail::Result<Self> {
+        let path = Self::default_path()
+            .or_fail_with(|()| "cannot determine the config directory".to_owned())?;
+        Self::load(path)
+    }
+
+    pub fn load(path: PathBuf) -> orfail::Result<Self> {
+        if !path.exists() {
+            return Ok(Self {
+                path,
+                clipboards: BTreeMap::new(),
+            });
+        }
+
+        let content = std::fs::read_to_string(&path).or_fail()?;
+        let nojson::Json(clipboards) = content
+            .parse::<nojson::Json<BTreeMap<String, Clipboard>>>()
+            .or_fail_with(|e| format!("invalid registers file {}: {e}", path.display()))?;
+        Ok(Self { path, clipboards })
+    }
+
+    pub fn save(&self) -> orfail::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir).or_fail()?;
+        }
+        let content = nojson::Json(&self.clipboards).to_string();
+        std::fs::write(&self.path, content).or_fail()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::buffer::TextPosition;
+
+    use super::*;
+
+    #[test]
+    fn save_and_load() -> orfail::Result<()> {
+        let dir = std::env::temp_dir().join(format!("utu-registers-{}", std::process::id()));
+        let path = dir.join("utu").join("registers.json");
+
+        let mut registers = Registers::load(path.clone()).or_fail()?;
+        assert!(registers.clipboards.is_empty());
+
+        let cursor = TextPosition { row: 3, col: 4 };
+        let fg_chars = ['.', 'a', '🟨'].into_iter().collect();
+        let clipboard = Clipboard::from_text(".a\n🟨", cursor, '.', &fg_chars).or_fail()?;
+        registers.clipboards.insert("a".to_owned(), clipboard);
+        registers.save().or_fail()?;
+
+        let registers = Registers::load(path).or_fail()?;
+        let clipboard = registers.clipboards.get("a").or_fail()?;
+        assert_eq!(clipboard.original_cursor, cursor);
+        assert_eq!(clipboard.to_text('.'), ".a\n🟨.\n");
+
+        std::fs::remove_dir_all(&dir).or_fail()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,284 @@
+use std::collections::BTreeSet;
+
+use crate::buffer::TextPosition;
+
+// How marked positions are combined with the current selection
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelectionOp {
+    Add,
+    Subtract,
+    Intersect,
+}
+
+impl std::fmt::Display for SelectionOp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SelectionOp::Add => write!(f, "add"),
+            SelectionOp::Subtract => write!(f, "subtract"),
+            SelectionOp::Intersect => write!(f, "intersect"),
+        }
+    }
+}
+
+impl std::str::FromStr for SelectionOp {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "add" => Ok(SelectionOp::Add),
+            "subtract" => Ok(SelectionOp::Subtract),
+            "intersect" => Ok(SelectionOp::Intersect),
+            _ => Err(format!("unknown selection operation: {s}")),
+        }
+    }
+}
+
+// Set of neighbours used by `SelectionMorph`
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Neighborhood {
+    #[default]
+    Four,
+    Eight,
+}
+
+impl Neighborhood {
+    // `None` stands for a neighbor beyond the top or left edge
+    fn neighbors(self, pos: TextPosition) -> impl Iterator<Item = Option<TextPosition>> {
+        let offsets: &[(isize, isize)] = match self {
+            Neighborhood::Four => &[(-1, 0), (1, 0), (0, -1), (0, 1)],
+            Neighborhood::Eight => &[
+                (-1, -1),
+                (-1, 0),
+                (-1, 1),
+                (0, -1),
+                (0, 1),
+                (1, -1),
+                (1, 0),
+                (1, 1),
+            ],
+        };
+        offsets.iter().map(move |&(dr, dc)| {
+            Some(TextPosition {
+                row: pos.row.checked_add_signed(dr)?,
+                col: pos.col.checked_add_signed(dc)?,
+            })
+        })
+    }
+}
+
+impl std::fmt::Display for Neighborhood {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Neighborhood::Four => write!(f, "4"),
+            Neighborhood::Eight => write!(f, "8"),
+        }
+    }
+}
+
+impl std::str::FromStr for Neighborhood {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "4" => Ok(Neighborhood::Four),
+            "8" => Ok(Neighborhood::Eight),
+            _ => Err(format!("unknown neighborhood: {s}")),
+        }
+    }
+}
+
+// Morphological operations on a set of pixel positions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelectionMorph {
+    Grow,
+    Shrink,
+    // Pixels of the set that touch a pixel outside of it
+    InnerOutline,
+    // Pixels outside of the set that touch a pixel of it
+    OuterOutline,
+}
+
+impl SelectionMorph {
+    pub fn apply(
+        self,
+        pixels: &BTreeSet<TextPosition>,
+        neighborhood: Neighborhood,
+    ) -> BTreeSet<TextPosition> {
+        let is_inner = |pos: &TextPosition| {
+            neighborhood
+                .neighbors(*pos)
+                .all(|p| p.is_some_and(|p| pixels.contains(&p)))
+        };
+        match self {
+            SelectionMorph::Grow => pixels
+                .iter()
+                .flat_map(|&pos| std::iter::once(pos).chain(neighborhood.neighbors(pos).flatten()))
+                .collect(),
+            SelectionMorph::Shrink => pixels.iter().copied().filter(is_inner).collect(),
+            SelectionMorph::InnerOutline => {
+                pixels.iter().copied().filter(|p| !is_inner(p)).collect()
+            }
+            SelectionMorph::OuterOutline => pixels
+                .iter()
+                .flat_map(|&pos| neighborhood.neighbors(pos).flatten())
+                .filter(|pos| !pixels.contains(pos))
+                .collect(),
+        }
+    }
+}
+
+impl std::fmt::Display for SelectionMorph {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SelectionMorph::Grow => write!(f, "grow"),
+            SelectionMorph::Shrink => write!(f, "shrink"),
+            SelectionMorph::InnerOutline => write!(f, "inner-outline"),
+            SelectionMorph::OuterOutline => write!(f, "outer-outline"),
+        }
+    }
+}
+
+impl std::str::FromStr for SelectionMorph {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "grow" => Ok(SelectionMorph::Grow),
+            "shrink" => Ok(SelectionMorph::Shrink),
+            "inner-outline" => Ok(SelectionMorph::InnerOutline),
+            "outer-outline" => Ok(SelectionMorph::OuterOutline),
+            _ => Err(format!("unknown selection morph: {s}")),
+        }
+    }
+}
+
+// Pixel positions that stay selected across markers (see `TextBuffer::to_pixel_position()`)
+#[derive(Debug, Default, Clone)]
+pub struct Selection {
+    positions: BTreeSet<TextPosition>,
+}
+
+impl Selection {
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn positions(&self) -> impl '_ + Iterator<Item = TextPosition> {
+        self.positions.iter().copied()
+    }
+
+    pub fn clear(&mut self) {
+        self.positions.clear();
+    }
+
+    pub fn apply(&mut self, op: SelectionOp, positions: impl IntoIterator<Item = TextPosition>) {
+        let positions: BTreeSet<_> = positions.into_iter().collect();
+        match op {
+            SelectionOp::Add => self.positions.extend(positions),
+            SelectionOp::Subtract => self.positions.retain(|pos| !positions.contains(pos)),
+            SelectionOp::Intersect => self.positions.retain(|pos| positions.contains(pos)),
+        }
+    }
+
+    // Inverts the selection within `area` (positions outside of it are kept as is)
+    pub fn invert(&mut self, area: impl IntoIterator<Item = TextPosition>) {
+        for pos in area {
+            if !self.positions.remove(&pos) {
+                self.positions.insert(pos);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn positions(cols: &[usize]) -> Vec<TextPosition> {
+        cols.iter()
+            .map(|&col| TextPosition { row: 0, col })
+            .collect()
+    }
+
+    #[test]
+    fn set_operations() {
+        let mut selection = Selection::default();
+        selection.apply(SelectionOp::Add, positions(&[0, 1, 2]));
+        selection.apply(SelectionOp::Add, positions(&[2, 3]));
+        assert_eq!(
+            selection.positions().collect::<Vec<_>>(),
+            positions(&[0, 1, 2, 3])
+        );
+
+        selection.apply(SelectionOp::Subtract, positions(&[1, 5]));
+        assert_eq!(
+            selection.positions().collect::<Vec<_>>(),
+            positions(&[0, 2, 3])
+        );
+
+        selection.apply(SelectionOp::Intersect, positions(&[2, 3, 4]));
+        assert_eq!(
+            selection.positions().collect::<Vec<_>>(),
+            positions(&[2, 3])
+        );
+
+        selection.invert(positions(&[0, 1, 2]));
+        assert_eq!(
+            selection.positions().collect::<Vec<_>>(),
+            positions(&[0, 1, 3])
+        );
+    }
+
+    #[test]
+    fn follows_pixels() -> orfail::Result<()> {
+        use orfail::OrFail;
+
+        use crate::{config::Config, editor::Editor};
+
+        let mut editor = Editor::new("test.txt".into(), Config::default()).or_fail()?;
+        editor.buffer.filter.fg_chars = ['.', '🟨'].into_iter().collect();
+        editor.buffer.set_text("...\n".to_owned());
+        let pos = |col| TextPosition { row: 0, col };
+
+        // The selection follows the pixels when a wide char is painted before them
+        editor.select(SelectionOp::Add, vec![pos(1), pos(2)]);
+        editor.buffer.update(pos(0), '🟨');
+        assert_eq!(editor.selected_positions(), [pos(2), pos(3)]);
+        Ok(())
+    }
+
+    #[test]
+    fn morphs() {
+        let pixels: BTreeSet<_> = (1..4)
+            .flat_map(|row| (1..4).map(move |col| TextPosition { row, col }))
+            .collect();
+        let render = |pixels: BTreeSet<TextPosition>| crate::test_util::render(&pixels, 5, 5);
+
+        let four = Neighborhood::Four;
+        let eight = Neighborhood::Eight;
+        assert_eq!(
+            render(SelectionMorph::Grow.apply(&pixels, four)),
+            [".###.", "#####", "#####", "#####", ".###."]
+        );
+        assert_eq!(
+            render(SelectionMorph::Grow.apply(&pixels, eight)),
+            ["#####", "#####", "#####", "#####", "#####"]
+        );
+        assert_eq!(
+            render(SelectionMorph::Shrink.apply(&pixels, four)),
+            [".....", ".....", "..#..", ".....", "....."]
+        );
+        assert_eq!(
+            render(SelectionMorph::InnerOutline.apply(&pixels, four)),
+            [".....", ".###.", ".#.#.", ".###.", "....."]
+        );
+        assert_eq!(
+            render(SelectionMorph::OuterOutline.apply(&pixels, four)),
+            [".###.", "#...#", "#...#", "#...#", ".###."]
+        );
+    }
+}
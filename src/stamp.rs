@@ -0,0 +1,122 @@
+use std::path::Path;
+
+use orfail::OrFail;
+
+use crate::{
+    buffer::{TextBuffer, TextBufferFilter, TextPosition},
+    clipboard::Clipboard,
+};
+
+// Small sprite loaded from a plain text file in the stamp directory
+#[derive(Debug)]
+pub struct Stamp {
+    pub name: String,
+    pub buffer: TextBuffer,
+}
+
+impl Stamp {
+    // Loads the non-hidden files in `dir` sorted by their names.
+    // Unreadable files (e.g., non UTF-8 ones) are skipped, and so are files containing chars
+    // outside of `filter.fg_chars` (their names are returned as the second element).
+    pub fn load_dir(
+        dir: &Path,
+        filter: &TextBufferFilter,
+    ) -> orfail::Result<(Vec<Self>, Vec<String>)> {
+        let mut stamps = Vec::new();
+        let mut invalid = Vec::new();
+        for entry in std::fs::read_dir(dir)
+            .or_fail_with(|e| format!("failed to read stamp directory {}: {e}", dir.display()))?
+        {
+            let Ok(entry) = entry else {
+                continue;
+            };
+            let path = entry.path();
+            let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+                continue;
+            };
+            if name.starts_with('.') || !path.is_file() {
+                continue;
+            }
+
+            let Ok(text) = std::fs::read_to_string(&path) else {
+                continue;
+            };
+            if !text
+                .lines()
+                .flat_map(|line| line.chars())
+                .all(|c| filter.fg_chars.contains(&c))
+            {
+                invalid.push(name.to_owned());
+                continue;
+            }
+            let mut buffer = TextBuffer::new();
+            buffer.filter = filter.clone();
+            buffer.set_text(text);
+            stamps.push(Self {
+                name: name.to_owned(),
+                buffer,
+            });
+        }
+        stamps.sort_by(|a, b| a.name.cmp(&b.name));
+        invalid.sort();
+        Ok((stamps, invalid))
+    }
+
+    pub fn to_clipboard(&self, cursor: TextPosition, canvas_char: char) -> Option<Clipboard> {
+        let text = self.buffer.lines().collect::<Vec<_>>().join("\n");
+        Clipboard::from_text(&text, cursor, canvas_char, &self.buffer.filter.fg_chars)
+    }
+}
+
+#[derive(Debug)]
+pub struct StampPicker {
+    pub stamps: Vec<Stamp>,
+    pub selected: usize,
+}
+
+impl StampPicker {
+    pub fn selected_stamp(&self) -> Option<&Stamp> {
+        self.stamps.get(self.selected)
+    }
+
+    // Moves the selection to the previous (`delta < 0`) or next stamp
+    pub fn move_cursor(&mut self, delta: isize) {
+        self.selected = self
+            .selected
+            .saturating_add_signed(delta)
+            .min(self.stamps.len().saturating_sub(1));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn load_stamps() -> orfail::Result<()> {
+        let dir = std::env::temp_dir().join(format!("utu-stamps-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).or_fail()?;
+        std::fs::write(dir.join("tree.txt"), " o \nooo\n # \n").or_fail()?;
+        std::fs::write(dir.join("rock.txt"), "oo\n").or_fail()?;
+        std::fs::write(dir.join(".hidden"), "o\n").or_fail()?;
+        std::fs::write(dir.join("binary.png"), [0x89, 0x50, 0xff, 0xfe]).or_fail()?;
+        std::fs::write(dir.join("sign.txt"), "#x#\n").or_fail()?;
+
+        let filter = TextBufferFilter {
+            fg_chars: [' ', 'o', '#'].into_iter().collect(),
+            ..Default::default()
+        };
+        let (stamps, invalid) = Stamp::load_dir(&dir, &filter).or_fail()?;
+        let names: Vec<_> = stamps.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["rock.txt", "tree.txt"]);
+        assert_eq!(invalid, ["sign.txt"]);
+
+        let cursor = TextPosition { row: 2, col: 3 };
+        let clipboard = stamps[1].to_clipboard(cursor, ' ').or_fail()?;
+        assert_eq!(clipboard.pixel_count(), 5);
+        assert_eq!(clipboard.to_text(' '), " o \nooo\n # \n");
+
+        std::fs::remove_dir_all(&dir).or_fail()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,137 @@
+use crate::buffer::TextPosition;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SymmetryMode {
+    #[default]
+    Off,
+    // Mirrors left and right across a vertical axis
+    Horizontal,
+    // Mirrors top and bottom across a horizontal axis
+    Vertical,
+    Both,
+    // Rotates by 90 degrees around the center
+    Radial,
+}
+
+impl SymmetryMode {
+    // Returns `pos` and its mirrored copies.
+    // `center` is in half-pixel units so that the axis can lie between two pixels.
+    pub fn mirror(self, pos: TextPosition, center: TextPosition) -> Vec<TextPosition> {
+        let y = 2 * pos.row as isize - center.row as isize;
+        let x = 2 * pos.col as isize - center.col as isize;
+        let offsets = match self {
+            SymmetryMode::Off => vec![(y, x)],
+            SymmetryMode::Horizontal => vec![(y, x), (y, -x)],
+            SymmetryMode::Vertical => vec![(y, x), (-y, x)],
+            SymmetryMode::Both => vec![(y, x), (y, -x), (-y, x), (-y, -x)],
+            SymmetryMode::Radial => vec![(y, x), (x, -y), (-y, -x), (-x, y)],
+        };
+        let mut positions: Vec<_> = offsets
+            .into_iter()
+            .filter_map(|(y, x)| {
+                let row = y + center.row as isize;
+                let col = x + center.col as isize;
+                // Rotated positions may fall between pixels on non-square canvases
+                (row >= 0 && col >= 0 && row % 2 == 0 && col % 2 == 0).then_some(TextPosition {
+                    row: row as usize / 2,
+                    col: col as usize / 2,
+                })
+            })
+            .collect();
+        positions.sort();
+        positions.dedup();
+        positions
+    }
+
+    // Whether the pixel at `pos` is on an axis (or right after it if the axis lies between pixels)
+    pub fn is_on_axis(self, pos: TextPosition, center: TextPosition) -> bool {
+        let (vertical, horizontal) = match self {
+            SymmetryMode::Off => (false, false),
+            SymmetryMode::Horizontal => (true, false),
+            SymmetryMode::Vertical => (false, true),
+            SymmetryMode::Both | SymmetryMode::Radial => (true, true),
+        };
+        let on = |p: usize, c: usize| 2 * p == c || 2 * p == c + 1;
+        (vertical && on(pos.col, center.col)) || (horizontal && on(pos.row, center.row))
+    }
+}
+
+impl std::fmt::Display for SymmetryMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SymmetryMode::Off => write!(f, "off"),
+            SymmetryMode::Horizontal => write!(f, "horizontal"),
+            SymmetryMode::Vertical => write!(f, "vertical"),
+            SymmetryMode::Both => write!(f, "both"),
+            SymmetryMode::Radial => write!(f, "radial"),
+        }
+    }
+}
+
+impl std::str::FromStr for SymmetryMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "off" => Ok(SymmetryMode::Off),
+            "horizontal" => Ok(SymmetryMode::Horizontal),
+            "vertical" => Ok(SymmetryMode::Vertical),
+            "both" => Ok(SymmetryMode::Both),
+            "radial" => Ok(SymmetryMode::Radial),
+            _ => Err(format!("unknown symmetry mode: {s}")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pos(row: usize, col: usize) -> TextPosition {
+        TextPosition { row, col }
+    }
+
+    #[test]
+    fn mirror() {
+        // 4x4 canvas: the axes lie between pixels
+        let center = pos(3, 3);
+        assert_eq!(SymmetryMode::Off.mirror(pos(0, 1), center), [pos(0, 1)]);
+        assert_eq!(
+            SymmetryMode::Horizontal.mirror(pos(0, 1), center),
+            [pos(0, 1), pos(0, 2)]
+        );
+        assert_eq!(
+            SymmetryMode::Vertical.mirror(pos(0, 1), center),
+            [pos(0, 1), pos(3, 1)]
+        );
+        assert_eq!(
+            SymmetryMode::Both.mirror(pos(0, 1), center),
+            [pos(0, 1), pos(0, 2), pos(3, 1), pos(3, 2)]
+        );
+        assert_eq!(
+            SymmetryMode::Radial.mirror(pos(0, 1), center),
+            [pos(0, 1), pos(1, 3), pos(2, 0), pos(3, 2)]
+        );
+
+        // 3x3 canvas: the center pixel is its own mirror
+        let center = pos(2, 2);
+        assert_eq!(SymmetryMode::Radial.mirror(pos(1, 1), center), [pos(1, 1)]);
+        assert!(SymmetryMode::Horizontal.is_on_axis(pos(0, 1), center));
+        assert!(!SymmetryMode::Horizontal.is_on_axis(pos(1, 0), center));
+    }
+
+    #[test]
+    fn canvas_center() -> orfail::Result<()> {
+        use orfail::OrFail;
+
+        use crate::{config::Config, editor::Editor};
+
+        let mut editor = Editor::new("test.txt".into(), Config::default()).or_fail()?;
+        editor.buffer.filter.fg_chars = ['.', '🟨'].into_iter().collect();
+
+        // The center is based on the widest row in pixels
+        editor.buffer.set_text("......\n🟨🟨🟨\n".to_owned());
+        assert_eq!(editor.symmetry_center(), Some(pos(1, 5)));
+        Ok(())
+    }
+}
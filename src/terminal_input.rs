@@ -0,0 +1,436 @@
+use std::os::fd::RawFd;
+
+use tuinix::{KeyCode, KeyInput, Terminal};
+
+// Bracketed paste markers (see `system_clipboard::ENABLE_BRACKETED_PASTE`)
+const PASTE_START: &[u8] = b"\x1b[200~";
+const PASTE_END: &[u8] = b"\x1b[201~";
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum InputEvent {
+    Key(KeyInput),
+    Paste(String),
+    Resize,
+}
+
+// Reads the raw terminal input by itself because tuinix (0.2) cannot report bracketed pastes:
+// its parser silently drops the `ESC [ 2` prefix of the paste markers and reports the rest as
+// `0`, `0`/`1` and `~` keys, which cannot be told apart from the same keys typed by hand.
+// Its reader and parser are private, and it reads ahead through the buffered `std::io::Stdin`,
+// so the markers cannot be picked out of `Terminal::input_fd()` before it consumes them either.
+#[derive(Debug, Default)]
+pub struct InputReader {
+    buf: Vec<u8>,
+    paste: Option<Vec<u8>>,
+}
+
+impl InputReader {
+    pub fn read_event(&mut self, terminal: &mut Terminal) -> std::io::Result<InputEvent> {
+        loop {
+            if let Some(event) = self.next_event() {
+                return Ok(event);
+            }
+            if !wait_for_input(terminal.input_fd(), terminal.signal_fd())? {
+                terminal.wait_for_resize()?;
+                return Ok(InputEvent::Resize);
+            }
+
+            let mut bytes = [0; 1024];
+            let size = read(terminal.input_fd(), &mut bytes)?;
+            if size == 0 {
+                return Err(std::io::ErrorKind::UnexpectedEof.into());
+            }
+            self.feed(&bytes[..size]);
+        }
+    }
+
+    fn feed(&mut self, bytes: &[u8]) {
+        self.buf.extend_from_slice(bytes);
+    }
+
+    fn next_event(&mut self) -> Option<InputEvent> {
+        loop {
+            if let Some(paste) = &mut self.paste {
+                let Some(end) = self
+                    .buf
+                    .windows(PASTE_END.len())
+                    .position(|w| w == PASTE_END)
+                else {
+                    // Keep the bytes that may be the beginning of the end marker
+                    let size = self.buf.len().saturating_sub(PASTE_END.len() - 1);
+                    paste.extend(self.buf.drain(..size));
+                    return None;
+                };
+                paste.extend(self.buf.drain(..end));
+                self.buf.drain(..PASTE_END.len());
+
+                // Terminals send line breaks as CR
+                let text = String::from_utf8_lossy(&self.paste.take().expect("infallible"))
+                    .replace("\r\n", "\n")
+                    .replace('\r', "\n");
+                return Some(InputEvent::Paste(text));
+            }
+
+            let (key, size) = parse_key(&self.buf)?;
+            if self.buf.drain(..size).eq(PASTE_START.iter().copied()) {
+                self.paste = Some(Vec::new());
+            } else if let Some(key) = key {
+                return Some(InputEvent::Key(key));
+            }
+        }
+    }
+}
+
+// Returns `false` if the terminal has been resized instead
+fn wait_for_input(input_fd: RawFd, signal_fd: RawFd) -> std::io::Result<bool> {
+    let mut fds = [input_fd, signal_fd].map(|fd| libc::pollfd {
+        fd,
+        events: libc::POLLIN,
+        revents: 0,
+    });
+    loop {
+        let ret = unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, -1) };
+        if ret == -1 {
+            let e = std::io::Error::last_os_error();
+            if e.kind() == std::io::ErrorKind::Interrupted {
+                continue;
+            }
+            return Err(e);
+        }
+        if fds[1].revents != 0 {
+            return Ok(false);
+        }
+        if fds[0].revents != 0 {
+            return Ok(true);
+        }
+    }
+}
+
+// `std::io::Stdin` is not used as it may buffer bytes that `poll()` no longer reports
+fn read(fd: RawFd, bytes: &mut [u8]) -> std::io::Result<usize> {
+    loop {
+        let ret = unsafe { libc::read(fd, bytes.as_mut_ptr().cast(), bytes.len()) };
+        if ret >= 0 {
+            return Ok(ret as usize);
+        }
+        let e = std::io::Error::last_os_error();
+        if e.kind() != std::io::ErrorKind::Interrupted {
+            return Err(e);
+        }
+    }
+}
+
+fn key(code: KeyCode) -> KeyInput {
+    KeyInput {
+        ctrl: false,
+        alt: false,
+        code,
+    }
+}
+
+// Accepts the key sequences that tuinix does (see the tests below) and skips unknown CSI sequences
+// as a whole instead of emitting their trailing bytes as keys.
+// Returns `None` if more bytes are needed, or `(None, size)` for `size` bytes of an unknown sequence.
+fn parse_key(bytes: &[u8]) -> Option<(Option<KeyInput>, usize)> {
+    match *bytes {
+        [] => None,
+        // A lone ESC at the end of the input is the Escape key itself
+        [0x1b] => Some((Some(key(KeyCode::Escape)), 1)),
+        [0x1b, b'[', ..] => parse_csi(bytes),
+        [0x1b, b'O'] => None,
+        [0x1b, b'O', c, ..] => Some((cursor_key_code(c).map(key), 3)),
+        [0x1b, c, ..] if c < 0x80 && c != 0x1b => {
+            let code = match c {
+                0x08 => KeyCode::Backspace,
+                _ => ascii_key(c).code,
+            };
+            let key = KeyInput {
+                ctrl: c < 0x20,
+                alt: true,
+                code,
+            };
+            Some((Some(key), 2))
+        }
+        [0x1b, ..] => Some((Some(key(KeyCode::Escape)), 1)),
+        [0x7f, ..] => Some((Some(key(KeyCode::Backspace)), 1)),
+        [c, ..] if c < 0x80 => Some((Some(ascii_key(c)), 1)),
+        [c, ..] => {
+            let size = match c {
+                0xc0..0xe0 => 2,
+                0xe0..0xf0 => 3,
+                0xf0..0xf8 => 4,
+                _ => return Some((None, 1)),
+            };
+            let Ok(s) = std::str::from_utf8(bytes.get(..size)?) else {
+                return Some((None, 1));
+            };
+            let c = s.chars().next().expect("infallible");
+            Some((Some(key(KeyCode::Char(c))), size))
+        }
+    }
+}
+
+fn ascii_key(c: u8) -> KeyInput {
+    match c {
+        b'\r' => key(KeyCode::Enter),
+        b'\t' => key(KeyCode::Tab),
+        0x00..0x20 => KeyInput {
+            ctrl: true,
+            alt: false,
+            code: KeyCode::Char((c + 0x60) as char),
+        },
+        _ => key(KeyCode::Char(c as char)),
+    }
+}
+
+fn cursor_key_code(c: u8) -> Option<KeyCode> {
+    match c {
+        b'A' => Some(KeyCode::Up),
+        b'B' => Some(KeyCode::Down),
+        b'C' => Some(KeyCode::Right),
+        b'D' => Some(KeyCode::Left),
+        b'H' => Some(KeyCode::Home),
+        b'F' => Some(KeyCode::End),
+        _ => None,
+    }
+}
+
+// `ESC [ <params> <final byte>` (e.g., `ESC [ A` or `ESC [ 5 ; 5 ~`)
+fn parse_csi(bytes: &[u8]) -> Option<(Option<KeyInput>, usize)> {
+    const MAX_LEN: usize = 16;
+
+    let Some(end) = bytes
+        .iter()
+        .skip(2)
+        .position(|b| (0x40..0x7f).contains(b))
+        .map(|i| i + 2)
+    else {
+        // Gives up on a sequence that never ends
+        return (bytes.len() >= MAX_LEN).then_some((None, bytes.len()));
+    };
+    let size = end + 1;
+
+    let params = std::str::from_utf8(&bytes[2..end]).ok();
+    let mut params = params.into_iter().flat_map(|s| s.split(';'));
+    let number = params.next().filter(|s| !s.is_empty());
+    let modifier = params
+        .next()
+        .and_then(|s| s.parse::<u8>().ok())
+        .unwrap_or(1);
+    if params.next().is_some() {
+        return Some((None, size));
+    }
+
+    let code = match (bytes[end], number) {
+        (b'Z', None) => Some(KeyCode::BackTab),
+        (b'~', Some("1" | "7")) => Some(KeyCode::Home),
+        (b'~', Some("2")) => Some(KeyCode::Insert),
+        (b'~', Some("3")) => Some(KeyCode::Delete),
+        (b'~', Some("4" | "8")) => Some(KeyCode::End),
+        (b'~', Some("5")) => Some(KeyCode::PageUp),
+        (b'~', Some("6")) => Some(KeyCode::PageDown),
+        (c, None | Some("1")) => cursor_key_code(c),
+        _ => None,
+    };
+
+    // The modifier is one plus the bitmask of Shift (1), Alt (2) and Ctrl (4)
+    let modifier = modifier.saturating_sub(1);
+    let key = code.map(|code| KeyInput {
+        ctrl: modifier & 4 != 0,
+        alt: modifier & 2 != 0,
+        code,
+    });
+    Some((key, size))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn events(reader: &mut InputReader, bytes: &[u8]) -> Vec<InputEvent> {
+        reader.feed(bytes);
+        std::iter::from_fn(|| reader.next_event()).collect()
+    }
+
+    fn keys(s: &str) -> Vec<InputEvent> {
+        s.chars()
+            .map(|c| InputEvent::Key(key(KeyCode::Char(c))))
+            .collect()
+    }
+
+    #[test]
+    fn parse_keys() {
+        let mut reader = InputReader::default();
+        assert_eq!(events(&mut reader, "a🟨".as_bytes()), keys("a🟨"));
+        assert_eq!(
+            events(&mut reader, b"\r\x01\x1bx\x1b[A\x1b[5~\x1b[1;5C\x7f"),
+            [
+                key(KeyCode::Enter),
+                KeyInput {
+                    ctrl: true,
+                    alt: false,
+                    code: KeyCode::Char('a'),
+                },
+                KeyInput {
+                    ctrl: false,
+                    alt: true,
+                    code: KeyCode::Char('x'),
+                },
+                key(KeyCode::Up),
+                key(KeyCode::PageUp),
+                KeyInput {
+                    ctrl: true,
+                    alt: false,
+                    code: KeyCode::Right,
+                },
+                key(KeyCode::Backspace),
+            ]
+            .map(InputEvent::Key)
+        );
+        assert_eq!(
+            events(&mut reader, b"\x1b"),
+            [InputEvent::Key(key(KeyCode::Escape))]
+        );
+
+        // Split sequences wait for the rest
+        assert_eq!(events(&mut reader, b"\x1b[1;"), []);
+        assert_eq!(
+            events(&mut reader, b"5D"),
+            [InputEvent::Key(KeyInput {
+                ctrl: true,
+                alt: false,
+                code: KeyCode::Left,
+            })]
+        );
+        assert_eq!(events(&mut reader, &"🟨".as_bytes()[..2]), []);
+        assert_eq!(events(&mut reader, &"🟨".as_bytes()[2..]), keys("🟨"));
+    }
+
+    fn key_with(ctrl: bool, alt: bool, code: KeyCode) -> InputEvent {
+        InputEvent::Key(KeyInput { ctrl, alt, code })
+    }
+
+    #[test]
+    fn parse_special_keys() {
+        let mut reader = InputReader::default();
+        assert_eq!(
+            events(
+                &mut reader,
+                b"\t\x1b[Z\x1b[H\x1b[F\x1b[1~\x1b[7~\x1b[4~\x1b[8~\x1b[2~\x1b[3~\x1b[6~"
+            ),
+            [
+                KeyCode::Tab,
+                KeyCode::BackTab,
+                KeyCode::Home,
+                KeyCode::End,
+                KeyCode::Home,
+                KeyCode::Home,
+                KeyCode::End,
+                KeyCode::End,
+                KeyCode::Insert,
+                KeyCode::Delete,
+                KeyCode::PageDown,
+            ]
+            .map(|code| key_with(false, false, code))
+        );
+
+        // `ESC O` cursor keys
+        assert_eq!(
+            events(&mut reader, b"\x1bOA\x1bOB\x1bOC\x1bOD\x1bOH\x1bOF"),
+            [
+                KeyCode::Up,
+                KeyCode::Down,
+                KeyCode::Right,
+                KeyCode::Left,
+                KeyCode::Home,
+                KeyCode::End,
+            ]
+            .map(|code| key_with(false, false, code))
+        );
+        assert_eq!(events(&mut reader, b"\x1bO"), []);
+        assert_eq!(
+            events(&mut reader, b"B"),
+            [key_with(false, false, KeyCode::Down)]
+        );
+    }
+
+    #[test]
+    fn parse_modifiers() {
+        let mut reader = InputReader::default();
+
+        // Shift (2) is ignored, Alt (3), Ctrl (5) and both (7)
+        assert_eq!(
+            events(
+                &mut reader,
+                b"\x1b[1;2A\x1b[1;3B\x1b[1;5C\x1b[1;7D\x1b[3;5~\x1b[5;3~"
+            ),
+            [
+                key_with(false, false, KeyCode::Up),
+                key_with(false, true, KeyCode::Down),
+                key_with(true, false, KeyCode::Right),
+                key_with(true, true, KeyCode::Left),
+                key_with(true, false, KeyCode::Delete),
+                key_with(false, true, KeyCode::PageUp),
+            ]
+        );
+
+        // Alt + char, Alt + Ctrl + char, Alt + Enter and Alt + Backspace
+        assert_eq!(
+            events(&mut reader, b"\x1bA\x1b\x01\x1b\r\x1b\x08"),
+            [
+                key_with(false, true, KeyCode::Char('A')),
+                key_with(true, true, KeyCode::Char('a')),
+                key_with(true, true, KeyCode::Enter),
+                key_with(true, true, KeyCode::Backspace),
+            ]
+        );
+
+        // ESC followed by ESC or a non-ASCII byte is the Escape key
+        assert_eq!(
+            events(&mut reader, "\x1b\x1b\x1b🟨".as_bytes()),
+            [
+                key_with(false, false, KeyCode::Escape),
+                key_with(false, false, KeyCode::Escape),
+                key_with(false, false, KeyCode::Escape),
+                key_with(false, false, KeyCode::Char('🟨')),
+            ]
+        );
+    }
+
+    #[test]
+    fn skip_unknown_sequences() {
+        let mut reader = InputReader::default();
+
+        // Unknown CSI and `ESC O` sequences are dropped as a whole
+        assert_eq!(
+            events(&mut reader, b"\x1b[15~a\x1b[1;2;3Ab\x1b[?1uc\x1bOPd"),
+            keys("abcd")
+        );
+
+        // Invalid UTF-8 bytes are dropped one by one
+        assert_eq!(events(&mut reader, b"\xffa\x80\xe3\x81b"), keys("ab"));
+
+        // A CSI sequence that never ends is given up after a while
+        assert_eq!(events(&mut reader, b"\x1b[1;1;1;1;1;1;1"), []);
+        assert_eq!(events(&mut reader, b";"), []);
+        assert_eq!(events(&mut reader, b"a"), keys("a"));
+    }
+
+    #[test]
+    fn bracketed_paste() {
+        let mut reader = InputReader::default();
+
+        // Typed marker-like keys are not a paste
+        assert_eq!(events(&mut reader, b"00~"), keys("00~"));
+
+        assert_eq!(events(&mut reader, b"x\x1b[200~ab\rc"), keys("x"));
+        assert_eq!(events(&mut reader, b"01~d\x1b[20"), []);
+        assert_eq!(
+            events(&mut reader, b"1~y"),
+            [
+                InputEvent::Paste("ab\nc01~d".to_owned()),
+                InputEvent::Key(key(KeyCode::Char('y'))),
+            ]
+        );
+    }
+}
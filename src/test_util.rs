@@ -0,0 +1,25 @@
+use std::collections::BTreeSet;
+
+use crate::buffer::TextPosition;
+
+// Renders `positions` within a `rows`x`cols` grid as '#' (included) and '.' (excluded) lines
+pub fn render<'a>(
+    positions: impl IntoIterator<Item = &'a TextPosition>,
+    rows: usize,
+    cols: usize,
+) -> Vec<String> {
+    let positions: BTreeSet<_> = positions.into_iter().collect();
+    (0..rows)
+        .map(|row| {
+            (0..cols)
+                .map(|col| {
+                    if positions.contains(&TextPosition { row, col }) {
+                        '#'
+                    } else {
+                        '.'
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
@@ -0,0 +1,134 @@
+use std::path::{Path, PathBuf};
+
+use orfail::OrFail;
+
+use crate::history::UndoHistory;
+
+// Bumped whenever the serialized history changes (journals without a version are the linear format)
+const FORMAT_VERSION: u32 = 2;
+
+// Hidden sidecar file (`.{FILE_NAME}.utu-undo`) that keeps the undo history of a saved file.
+#[derive(Debug)]
+pub struct UndoJournal {
+    pub path: PathBuf,
+}
+
+impl UndoJournal {
+    pub fn new(file_path: &Path) -> Self {
+        let file_name = file_path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        Self {
+            path: file_path.with_file_name(format!(".{file_name}.utu-undo")),
+        }
+    }
+
+    // Returns `None` if there is no journal or the file was modified after the journal was written.
+    pub fn load(&self, text: &str) -> orfail::Result<Option<UndoHistory>> {
+        if !self.path.exists() {
+            return Ok(None);
+        }
+
+        let content = std::fs::read_to_string(&self.path).or_fail()?;
+        let json = nojson::RawJson::parse(&content).or_fail()?;
+        let value = json.value();
+        let version: Option<u32> = value
+            .to_member("version")
+            .and_then(|v| v.map(|v| v.try_into()))
+            .or_fail()?;
+        (version == Some(FORMAT_VERSION)).or_fail_with(|()| {
+            format!("unsupported undo journal version: {}", version.unwrap_or(1))
+        })?;
+        let text_hash: u64 = value
+            .to_member("text_hash")
+            .and_then(|v| v.required()?.try_into())
+            .or_fail()?;
+        if text_hash != fnv1a(text) {
+            return Ok(None);
+        }
+
+        let history: UndoHistory = value
+            .to_member("history")
+            .and_then(|v| v.required()?.try_into())
+            .or_fail()?;
+        // Otherwise undoing or redoing line operations would go out of bounds
+        history
+            .fits_rows(text.lines().count())
+            .or_fail_with(|()| "undo journal does not match the file".to_owned())?;
+        Ok(Some(history))
+    }
+
+    pub fn save(&self, text: &str, history: &UndoHistory) -> orfail::Result<()> {
+        let content = nojson::json(|f| {
+            f.object(|f| {
+                f.member("version", FORMAT_VERSION)?;
+                f.member("text_hash", fnv1a(text))?;
+                f.member("history", history)
+            })
+        });
+        std::fs::write(&self.path, content.to_string()).or_fail()?;
+        Ok(())
+    }
+}
+
+// A stable hash is required here as the value is persisted across processes
+fn fnv1a(text: &str) -> u64 {
+    text.bytes().fold(0xcbf29ce484222325, |hash, b| {
+        (hash ^ u64::from(b)).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::buffer::{TextBuffer, TextPosition};
+
+    use super::*;
+
+    #[test]
+    fn save_and_load() -> orfail::Result<()> {
+        let dir = std::env::temp_dir().join(format!("utu-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).or_fail()?;
+        let journal = UndoJournal::new(&dir.join("canvas.md"));
+        assert_eq!(journal.path, dir.join(".canvas.md.utu-undo"));
+
+        let mut buffer = TextBuffer::new();
+        buffer.filter.fg_chars = [' ', 'o'].into_iter().collect();
+        buffer.set_text("|  |\n".to_owned());
+        buffer.update(TextPosition { row: 0, col: 1 }, 'o');
+        journal.save("|o |\n", buffer.history()).or_fail()?;
+
+        // Modified file
+        assert!(journal.load("|oo|\n").or_fail()?.is_none());
+
+        // Journals of other format versions are rejected
+        let old = r#"{"text_hash":0,"history":{"undo":[],"redo":[]}}"#;
+        std::fs::write(&journal.path, old).or_fail()?;
+        assert!(journal.load("|o |\n").is_err());
+        journal.save("|o |\n", buffer.history()).or_fail()?;
+
+        // Unmodified file
+        let history = journal.load("|o |\n").or_fail()?.or_fail()?;
+        buffer.set_text("|o |\n".to_owned());
+        buffer.set_history(history);
+        assert_eq!(buffer.undo(), Some(0));
+        assert_eq!(buffer.lines().collect::<Vec<_>>(), ["|  |"]);
+
+        // Line operations beyond the text are rejected (e.g., a hand-edited journal)
+        buffer.replace_lines(0, 1, vec!["|o |".to_owned(), "|oo|".to_owned()]);
+        journal.save("|o |\n", buffer.history()).or_fail()?;
+        assert!(journal.load("|o |\n").is_err());
+
+        // So are broken trees
+        journal.save("|o |\n|oo|\n", buffer.history()).or_fail()?;
+        assert!(journal.load("|o |\n|oo|\n").or_fail()?.is_some());
+        let content = std::fs::read_to_string(&journal.path).or_fail()?;
+        let broken = content.replacen(r#""last_child":2"#, r#""last_child":0"#, 1);
+        assert_ne!(content, broken);
+        std::fs::write(&journal.path, broken).or_fail()?;
+        assert!(journal.load("|o |\n|oo|\n").is_err());
+
+        std::fs::remove_dir_all(&dir).or_fail()?;
+        Ok(())
+    }
+}
@@ -5,7 +5,7 @@ use tuinix::{TerminalRegion, TerminalSize};
 
 use crate::{editor::Editor, tuinix_ext::TerminalFrame};
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct Legend {
     pub hide: bool,
 }
@@ -15,7 +15,7 @@ impl Legend {
     const SHOW_COLS: usize = 20;
 
     pub fn new() -> Self {
-        Self { hide: false }
+        Self::default()
     }
 
     pub fn render(&self, editor: &Editor, frame: &mut TerminalFrame) -> orfail::Result<()> {
@@ -29,15 +29,11 @@ impl Legend {
         }
 
         // Get actual possible commands based on current pending keys
-        let group = if editor.clipboard.is_some() {
-            &editor.config.keybindings.clipboard
-        } else {
-            &editor.config.keybindings.main
-        };
+        let group = editor.keybindings_group();
         let possible_commands: Vec<_> = editor
             .config
             .keybindings
-            .possible_commands(&group, &editor.pending_keys)
+            .possible_commands(group, &editor.pending_keys)
             .collect();
 
         // Draw the legend box
@@ -56,15 +52,11 @@ impl Legend {
         if self.hide {
             TerminalSize::rows_cols(1, Self::HIDE_COLS)
         } else {
-            let group = if editor.clipboard.is_some() {
-                &editor.config.keybindings.clipboard
-            } else {
-                &editor.config.keybindings.main
-            };
+            let group = editor.keybindings_group();
             let rows = 1 + editor
                 .config
                 .keybindings
-                .possible_commands(&group, &editor.pending_keys)
+                .possible_commands(group, &editor.pending_keys)
                 .count();
             TerminalSize::rows_cols(rows, Self::SHOW_COLS)
         }
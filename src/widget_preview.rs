@@ -3,7 +3,12 @@ use std::fmt::Write;
 use orfail::OrFail;
 use tuinix::{TerminalRegion, TerminalSize};
 
-use crate::{config::Color, editor::Editor, tuinix_ext::TerminalFrame};
+use crate::{
+    buffer::{TextBuffer, TextPosition},
+    config::{Color, Palette},
+    editor::Editor,
+    tuinix_ext::TerminalFrame,
+};
 
 #[derive(Debug, Default)]
 pub struct Preview {
@@ -58,72 +63,24 @@ impl Preview {
 
         for terminal_row in 0..content_height {
             write!(frame, "│").or_fail()?;
-
-            // Each terminal row represents 2 pixel rows (using ▄ character)
-            let pixel_row_top = viewport_start_row + terminal_row * 2;
-            let pixel_row_bottom = viewport_start_row + terminal_row * 2 + 1;
-
-            for pixel_col in 0..content_width {
-                let actual_pixel_col = viewport_start_col + pixel_col;
-
-                // Get colors for top and bottom pixels
-                let top_color = self.get_pixel_color(editor, pixel_row_top, actual_pixel_col);
-                let bottom_color = self.get_pixel_color(editor, pixel_row_bottom, actual_pixel_col);
-
-                // Convert to terminal colors
-                let top_terminal_color =
-                    tuinix::TerminalColor::new(top_color.r, top_color.g, top_color.b);
-                let bottom_terminal_color =
-                    tuinix::TerminalColor::new(bottom_color.r, bottom_color.g, bottom_color.b);
-
-                // Use ▄ character with foreground as bottom pixel and background as top pixel
-                write!(
-                    frame,
-                    "{}▄",
-                    tuinix::TerminalStyle::new()
-                        .fg_color(bottom_terminal_color)
-                        .bg_color(top_terminal_color)
-                )
-                .or_fail()?;
-            }
-
-            writeln!(frame, "{}", tuinix::TerminalStyle::RESET).or_fail()?;
+            let start = TextPosition {
+                row: viewport_start_row + terminal_row * 2,
+                col: viewport_start_col,
+            };
+            write_pixel_row(
+                frame,
+                &editor.buffer,
+                &editor.config.palette,
+                start,
+                content_width,
+            )
+            .or_fail()?;
+            writeln!(frame).or_fail()?;
         }
 
         Ok(())
     }
 
-    fn get_pixel_color(&self, editor: &Editor, pixel_row: usize, pixel_col: usize) -> Color {
-        let buffer = &editor.buffer;
-        let default_bg = Color::rgb(0xFF, 0xFF, 0xFF); // TODO: configurable
-        // let default_bg = Color::rgb(200, 200, 200); // TODO: configurable
-
-        // Check if we're within buffer bounds
-        if pixel_row >= buffer.rows() {
-            return default_bg;
-        }
-
-        // Get the character at this position
-        let text_pos = crate::buffer::TextPosition {
-            row: pixel_row,
-            col: pixel_col,
-        };
-
-        if let Some(ch) = buffer.get_char_at(text_pos) {
-            // Look up color in palette
-            return editor
-                .config
-                .palette
-                .colors
-                .get(&ch)
-                .copied()
-                .expect("TODO: validate too");
-        }
-
-        // Return background color for empty spaces or unmapped characters
-        default_bg
-    }
-
     pub fn size(&self, editor: &Editor) -> TerminalSize {
         if self.hide {
             TerminalSize::rows_cols(1, 4)
@@ -141,3 +98,70 @@ impl Preview {
             .take_right(preview_size.cols)
     }
 }
+
+// Writes a terminal row that represents the two pixel rows starting from `start`
+pub fn write_pixel_row(
+    frame: &mut TerminalFrame,
+    buffer: &TextBuffer,
+    palette: &Palette,
+    start: TextPosition,
+    width: usize,
+) -> orfail::Result<()> {
+    for pixel_col in 0..width {
+        let actual_pixel_col = start.col + pixel_col;
+
+        // Get colors for top and bottom pixels
+        let top_color = get_pixel_color(buffer, palette, start.row, actual_pixel_col);
+        let bottom_color = get_pixel_color(buffer, palette, start.row + 1, actual_pixel_col);
+
+        // Convert to terminal colors
+        let top_terminal_color = tuinix::TerminalColor::new(top_color.r, top_color.g, top_color.b);
+        let bottom_terminal_color =
+            tuinix::TerminalColor::new(bottom_color.r, bottom_color.g, bottom_color.b);
+
+        // Use ▄ character with foreground as bottom pixel and background as top pixel
+        write!(
+            frame,
+            "{}▄",
+            tuinix::TerminalStyle::new()
+                .fg_color(bottom_terminal_color)
+                .bg_color(top_terminal_color)
+        )
+        .or_fail()?;
+    }
+    write!(frame, "{}", tuinix::TerminalStyle::RESET).or_fail()?;
+    Ok(())
+}
+
+fn get_pixel_color(
+    buffer: &TextBuffer,
+    palette: &Palette,
+    pixel_row: usize,
+    pixel_col: usize,
+) -> Color {
+    let default_bg = Color::rgb(0xFF, 0xFF, 0xFF); // TODO: configurable
+    // let default_bg = Color::rgb(200, 200, 200); // TODO: configurable
+
+    // Check if we're within buffer bounds
+    if pixel_row >= buffer.rows() {
+        return default_bg;
+    }
+
+    // Get the character at this position
+    let text_pos = TextPosition {
+        row: pixel_row,
+        col: pixel_col,
+    };
+
+    if let Some(ch) = buffer.get_char_at(text_pos) {
+        // Look up color in palette
+        return palette
+            .colors
+            .get(&ch)
+            .copied()
+            .expect("TODO: validate too");
+    }
+
+    // Return background color for empty spaces or unmapped characters
+    default_bg
+}
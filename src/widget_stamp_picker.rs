@@ -0,0 +1,40 @@
+use orfail::OrFail;
+use tuinix::{TerminalRegion, TerminalSize};
+
+use crate::{
+    buffer::TextPosition, editor::Editor, tuinix_ext::TerminalFrame,
+    widget_thumbnail_list::ThumbnailList,
+};
+
+#[derive(Debug, Default)]
+pub struct StampPickerView;
+
+impl StampPickerView {
+    pub fn render(&self, editor: &Editor, frame: &mut TerminalFrame) -> orfail::Result<()> {
+        let Some(picker) = &editor.stamp_picker else {
+            return Ok(());
+        };
+        let Some(stamp) = picker.selected_stamp() else {
+            return Ok(());
+        };
+
+        let list = ThumbnailList {
+            len: picker.stamps.len(),
+            selected: picker.selected,
+            thumbnail: &stamp.buffer,
+            origin: TextPosition::default(),
+        };
+        list.render(frame, &editor.config.palette, |i| {
+            Ok(picker.stamps[i].name.clone())
+        })
+        .or_fail()
+    }
+
+    pub fn region(&self, editor: &Editor, size: TerminalSize) -> TerminalRegion {
+        let len = editor
+            .stamp_picker
+            .as_ref()
+            .map(|picker| picker.stamps.len());
+        ThumbnailList::region(len, size)
+    }
+}
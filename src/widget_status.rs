@@ -3,7 +3,7 @@ use std::fmt::Write;
 use orfail::OrFail;
 use tuinix::TerminalStyle;
 
-use crate::{editor::Editor, tuinix_ext::TerminalFrame};
+use crate::{brush::Brush, editor::Editor, symmetry::SymmetryMode, tuinix_ext::TerminalFrame};
 
 #[derive(Debug)]
 pub struct StatusLine;
@@ -15,22 +15,42 @@ impl StatusLine {
         let reset = TerminalStyle::RESET;
         let filler = " ".repeat(frame.size().cols);
 
+        let mode = if editor.undo_tree_cursor.is_some() {
+            "UNDO_TREE".to_owned()
+        } else if editor.stamp_picker.is_some() {
+            "STAMPS".to_owned()
+        } else if let Some(m) = &editor.marker {
+            m.name().to_owned()
+        } else if editor.clipboard.is_some() {
+            format!("CLIPBOARD:{}", editor.paste_mode)
+        } else {
+            "DRAW".to_owned()
+        };
+        let mode = if editor.brush == Brush::default() {
+            mode
+        } else {
+            format!("{mode} BRUSH({})", editor.brush)
+        };
+        let mode = if editor.symmetry == SymmetryMode::Off {
+            mode
+        } else {
+            format!("{mode} SYMMETRY({})", editor.symmetry)
+        };
+        let mode = if editor.selection.is_empty() {
+            mode
+        } else {
+            format!("{mode} SELECTION({})", editor.selection.len())
+        };
+
         // Show file status, cursor position, and mode information
         writeln!(
             frame,
-            "{style}{}[{}:{}:{}] [CANVAS:{:?}] {}{filler}{reset}",
+            "{style}{}[{}:{}:{}] [CANVAS:{:?}] {mode}{filler}{reset}",
             if editor.dirty.content { '*' } else { ' ' }, // Dirty indicator
             editor.path.file_name().and_then(|n| n.to_str()).or_fail()?,
             editor.cursor.row + 1,
             editor.cursor.col + 1,
             editor.config.keybindings.canvas_char(),
-            if let Some(m) = &editor.marker {
-                m.name()
-            } else if editor.clipboard.is_some() {
-                "CLIPBOARD"
-            } else {
-                "DRAW"
-            },
         )
         .or_fail()?;
 
@@ -1,21 +1,19 @@
 use std::{collections::BTreeSet, fmt::Write};
 
 use orfail::OrFail;
-use tuinix::TerminalStyle;
+use tuinix::{TerminalColor, TerminalStyle};
 use unicode_width::UnicodeWidthChar;
 
 use crate::{buffer::TextPosition, editor::Editor, tuinix_ext::TerminalFrame};
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct TextView {
     scroll_offset: TextPosition,
 }
 
 impl TextView {
     pub fn new() -> Self {
-        Self {
-            scroll_offset: TextPosition::default(),
-        }
+        Self::default()
     }
 
     pub fn render(&mut self, editor: &Editor, frame: &mut TerminalFrame) -> orfail::Result<()> {
@@ -25,11 +23,11 @@ impl TextView {
         self.adjust_scroll_offset_for_cursor(editor, terminal_size.rows, terminal_size.cols);
 
         // Collect all marked positions for efficient lookup
-        let marked_positions: BTreeSet<TextPosition> = editor
-            .marker
-            .as_ref()
-            .map(|marker| marker.marked_positions().collect())
-            .unwrap_or_default();
+        let marked_positions: BTreeSet<TextPosition> =
+            editor.selected_positions().into_iter().collect();
+        let canvas_char = editor.config.keybindings.canvas_char();
+        let symmetry_center = editor.symmetry_center();
+        let current_canvas = editor.current_canvas();
 
         // Render visible lines
         for (line_index, line) in editor
@@ -43,8 +41,9 @@ impl TextView {
             let mut current_col = self.scroll_offset.col;
 
             // Process each character in the visible portion of the line
-            for c in line
+            for (pixel_col, c) in line
                 .chars()
+                .enumerate()
                 .skip(self.scroll_offset.col)
                 .take(terminal_size.cols)
             {
@@ -53,10 +52,15 @@ impl TextView {
                     row: current_row,
                     col: current_col,
                 };
+                let pixel = TextPosition {
+                    row: current_row,
+                    col: pixel_col,
+                };
                 let (c, is_clipboard) = editor
                     .clipboard
                     .as_ref()
-                    .and_then(|cb| cb.get(position).map(|c| (c, true)))
+                    .and_then(|cb| cb.get_with_mode(pixel, editor.paste_mode, canvas_char))
+                    .map(|c| (c, true))
                     .unwrap_or((c, false));
                 current_col += c.width().unwrap_or_default();
 
@@ -70,6 +74,14 @@ impl TextView {
                     //let style = TerminalStyle::new().bg_color(TerminalColor::new(200, 200, 200));
                     let reset = TerminalStyle::RESET;
                     write!(frame, "{}{}{}", style, c, reset).or_fail()?;
+                } else if symmetry_center
+                    .is_some_and(|center| editor.symmetry.is_on_axis(pixel, center))
+                    && current_canvas.is_none_or(|canvas| canvas.contains(position))
+                {
+                    // Render the symmetry axis guide
+                    let style = TerminalStyle::new().bg_color(TerminalColor::BRIGHT_BLACK);
+                    let reset = TerminalStyle::RESET;
+                    write!(frame, "{}{}{}", style, c, reset).or_fail()?;
                 } else {
                     // Render normally
                     write!(frame, "{}", c).or_fail()?;
@@ -0,0 +1,88 @@
+use std::fmt::Write;
+
+use orfail::OrFail;
+use tuinix::{TerminalRegion, TerminalSize, TerminalStyle};
+
+use crate::{
+    buffer::{TextBuffer, TextPosition},
+    config::Palette,
+    tuinix_ext::TerminalFrame,
+    widget_preview::write_pixel_row,
+};
+
+// List of items followed by a thumbnail of the selected one (e.g., the undo tree and the stamp picker)
+#[derive(Debug)]
+pub struct ThumbnailList<'a> {
+    pub len: usize,
+    pub selected: usize,
+    pub thumbnail: &'a TextBuffer,
+    // Top-left pixel of `thumbnail` to show
+    pub origin: TextPosition,
+}
+
+impl ThumbnailList<'_> {
+    const COLS: usize = 28;
+    const MAX_LIST_ROWS: usize = 16;
+    const THUMBNAIL_ROWS: usize = 8;
+
+    // Size of the thumbnail in pixels
+    pub const THUMBNAIL_WIDTH: usize = Self::COLS - 1;
+    pub const THUMBNAIL_HEIGHT: usize = Self::THUMBNAIL_ROWS * 2;
+
+    // `item(i)` returns the line of the i-th item (only called for the visible ones)
+    pub fn render(
+        &self,
+        frame: &mut TerminalFrame,
+        palette: &Palette,
+        mut item: impl FnMut(usize) -> orfail::Result<String>,
+    ) -> orfail::Result<()> {
+        if frame.size().cols != Self::COLS {
+            return Ok(());
+        }
+
+        let list_rows = Self::list_rows(self.len);
+        let start = (self.selected + 1).saturating_sub(list_rows);
+        for i in (start..self.len).take(list_rows) {
+            let line = item(i).or_fail()?;
+            if i == self.selected {
+                let style = TerminalStyle::new().reverse();
+                let reset = TerminalStyle::RESET;
+                writeln!(frame, "│{style}{line}{reset}").or_fail()?;
+            } else {
+                writeln!(frame, "│{line}").or_fail()?;
+            }
+        }
+
+        writeln!(frame, "├{}", "─".repeat(Self::COLS - 1)).or_fail()?;
+        for terminal_row in 0..Self::THUMBNAIL_ROWS {
+            write!(frame, "│").or_fail()?;
+            let start = TextPosition {
+                row: self.origin.row + terminal_row * 2,
+                col: self.origin.col,
+            };
+            write_pixel_row(frame, self.thumbnail, palette, start, Self::THUMBNAIL_WIDTH)
+                .or_fail()?;
+            writeln!(frame).or_fail()?;
+        }
+        writeln!(frame, "└{}", "─".repeat(Self::COLS - 1)).or_fail()?;
+
+        Ok(())
+    }
+
+    fn list_rows(len: usize) -> usize {
+        len.min(Self::MAX_LIST_ROWS)
+    }
+
+    // Region at the top-left corner for a list of `len` items (`None` hides the list)
+    pub fn region(len: Option<usize>, size: TerminalSize) -> TerminalRegion {
+        let view_size = match len {
+            Some(len) => {
+                TerminalSize::rows_cols(Self::list_rows(len) + Self::THUMBNAIL_ROWS + 2, Self::COLS)
+            }
+            None => TerminalSize::rows_cols(0, 0),
+        };
+        size.to_region()
+            .take_top(view_size.rows)
+            .take_left(view_size.cols)
+    }
+}
@@ -0,0 +1,111 @@
+use std::time::SystemTime;
+
+use orfail::OrFail;
+use tuinix::{TerminalRegion, TerminalSize};
+
+use crate::{
+    buffer::TextPosition,
+    editor::Editor,
+    history::{UndoHistory, UndoNodeId},
+    tuinix_ext::TerminalFrame,
+    widget_thumbnail_list::ThumbnailList,
+};
+
+#[derive(Debug, Default)]
+pub struct UndoTreeView;
+
+impl UndoTreeView {
+    pub fn render(&self, editor: &Editor, frame: &mut TerminalFrame) -> orfail::Result<()> {
+        let Some(selected) = editor.undo_tree_cursor else {
+            return Ok(());
+        };
+
+        let history = editor.buffer.history();
+        let nodes = history.tree_order();
+        let selected_index = nodes.iter().position(|(id, _)| *id == selected).or_fail()?;
+        let now = SystemTime::now();
+
+        // Thumbnail of the selected state around the edited area
+        let snapshot = editor.buffer.snapshot(selected);
+        let center = Self::edited_center(history, selected).unwrap_or(editor.cursor);
+        let list = ThumbnailList {
+            len: nodes.len(),
+            selected: selected_index,
+            thumbnail: &snapshot,
+            origin: TextPosition {
+                row: center
+                    .row
+                    .saturating_sub(ThumbnailList::THUMBNAIL_HEIGHT / 2),
+                col: center
+                    .col
+                    .saturating_sub(ThumbnailList::THUMBNAIL_WIDTH / 2),
+            },
+        };
+        list.render(frame, &editor.config.palette, |i| {
+            let (id, depth) = nodes[i];
+            let node = history.node(id).or_fail()?;
+            let age = now.duration_since(node.timestamp).unwrap_or_default();
+            Ok(format!(
+                "{}{}#{} {}",
+                if id == history.current() { '*' } else { ' ' },
+                " ".repeat(depth.min(8)),
+                id,
+                format_age(age.as_secs()),
+            ))
+        })
+        .or_fail()
+    }
+
+    fn edited_center(history: &UndoHistory, id: UndoNodeId) -> Option<TextPosition> {
+        let node = history.node(id)?;
+        let (min, max) = node.operation.positions().fold(None, |acc, pos| {
+            let Some((min, max)): Option<(TextPosition, TextPosition)> = acc else {
+                return Some((pos, pos));
+            };
+            Some((
+                TextPosition {
+                    row: min.row.min(pos.row),
+                    col: min.col.min(pos.col),
+                },
+                TextPosition {
+                    row: max.row.max(pos.row),
+                    col: max.col.max(pos.col),
+                },
+            ))
+        })?;
+        Some(TextPosition {
+            row: (min.row + max.row) / 2,
+            col: (min.col + max.col) / 2,
+        })
+    }
+
+    pub fn region(&self, editor: &Editor, size: TerminalSize) -> TerminalRegion {
+        let len = editor
+            .undo_tree_cursor
+            .map(|_| editor.buffer.history().len());
+        ThumbnailList::region(len, size)
+    }
+
+    // Moves the selection to the previous (`delta < 0`) or next node in the listed order
+    pub fn move_cursor(editor: &mut Editor, delta: isize) {
+        let Some(selected) = editor.undo_tree_cursor else {
+            return;
+        };
+        let nodes = editor.buffer.history().tree_order();
+        let Some(i) = nodes.iter().position(|(id, _)| *id == selected) else {
+            return;
+        };
+        let i = i.saturating_add_signed(delta).min(nodes.len() - 1);
+        editor.undo_tree_cursor = Some(nodes[i].0);
+        editor.dirty.render = true;
+    }
+}
+
+fn format_age(secs: u64) -> String {
+    match secs {
+        0..60 => format!("{secs}s ago"),
+        60..3600 => format!("{}m ago", secs / 60),
+        3600..86400 => format!("{}h ago", secs / 3600),
+        _ => format!("{}d ago", secs / 86400),
+    }
+}